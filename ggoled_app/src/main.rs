@@ -1,16 +1,35 @@
 #![cfg_attr(not(debug_assertions), windows_subsystem = "windows")]
 
 mod os;
+#[cfg(feature = "server")]
+mod server;
+#[cfg(feature = "stats")]
+mod stats;
 
 use chrono::{DateTime, Local, TimeDelta, Timelike};
-use ggoled_draw::{bitmap_from_memory, DrawDevice, DrawEvent, LayerId, ShiftMode, TextOverflowMode, TextRenderer};
-use ggoled_lib::Device;
+use ggoled_draw::{
+    bitmap_from_memory, bitmap_from_memory_with_mode, frames_from_memory, frames_from_memory_with_mode, load_album_art,
+    DrawDevice, DrawEvent, DrawLayer, Frame, InversionSchedule, LayerId, ShiftMode, TextOverflowMode, TextRenderer,
+    TransitionConfig, TransitionKind,
+};
+use ggoled_lib::{bitmap::GrayMode, volume_from_percent, volume_to_percent, Device, BASE_STATION_VOLUME_MAX};
 use os::{capabilities, get_autostart, get_idle_seconds, set_autostart, Media, MediaControl, PlatformCapabilities};
-#[cfg(target_os = "macos")]
-use os::{ensure_accessibility_permission, start_volume_key_listener, VolumeKeySignal};
+#[cfg(any(target_os = "macos", target_os = "windows"))]
+use os::{
+    ensure_accessibility_permission, start_volume_key_listener, VolumeKeyListener, VolumeKeyMap, VolumeKeySignal,
+};
 use rfd::{MessageDialog, MessageLevel};
 use serde::{Deserialize, Serialize};
-use std::{path::PathBuf, sync::Arc, time::Duration};
+use std::{
+    collections::VecDeque,
+    fmt::Write,
+    path::{Path, PathBuf},
+    sync::{
+        mpsc::{channel, Receiver, TryRecvError},
+        Arc,
+    },
+    time::{Duration, Instant},
+};
 use tao::event::{Event, StartCause};
 use tao::event_loop::{ControlFlow, EventLoopBuilder};
 #[cfg(target_os = "macos")]
@@ -22,22 +41,54 @@ use tray_icon::{
 };
 
 const IDLE_TIMEOUT_SECS: usize = 60;
+const OLED_SHIFT_PERIOD_SECS: u64 = 90;
+const OLED_SHIFT_JITTER_RADIUS: isize = 2;
+// OLED shift modes offered in the tray menu
+const SHIFT_MODES: [(ConfigShiftMode, &str); 3] = [
+    (ConfigShiftMode::Off, "Off"),
+    (ConfigShiftMode::Simple, "Simple"),
+    (ConfigShiftMode::Jitter, "Jitter"),
+];
+const MIN_IDLE_TIMEOUT_SECS: usize = 5;
+// Screen brightness levels offered in the tray menu, out of the device's 1-10
+const BRIGHTNESS_PRESETS: [u8; 5] = [1, 3, 5, 7, 10];
+// Screensaver delays offered in the tray menu, with their labels
+const IDLE_TIMEOUT_PRESETS: [(usize, &str); 3] = [(15, "15 seconds"), (60, "1 minute"), (300, "5 minutes")];
 const NOTIF_DUR: Duration = Duration::from_secs(5);
 const TICK_DUR_FAST: Duration = Duration::from_millis(10);
+const TICK_DUR_ACTIVE: Duration = Duration::from_millis(50);
 const TICK_DUR_NORMAL: Duration = Duration::from_millis(250);
-const BASE_STATION_VOLUME_MAX: u8 = 56;
+const DEVICE_RETRY_PERIOD: Duration = Duration::from_secs(1);
+const CONFIG_CHECK_PERIOD: Duration = Duration::from_secs(1);
 const BASE_STATION_VOLUME_STEP: u8 = 4;
+// How long the device may take to report back a volume we set ourselves
+const VOLUME_ECHO_WINDOW: Duration = Duration::from_secs(1);
+const NOTIF_BLINK_DUR: Duration = Duration::from_millis(500);
+const NOTIF_QUEUE_MAX: usize = 4;
 const NOTIF_MARGIN_X: isize = 0;
 const NOTIF_MARGIN_Y: isize = 0;
-
-#[cfg(target_os = "macos")]
+const IMAGE_THRESHOLD: u8 = 0x80;
+// The headset reports its battery level in steps from 0 to 8
+const BATTERY_LEVEL_MAX: u8 = 8;
+const TIME_FORMAT: &str = "%I:%M %p";
+// Frames a clock or media text transition takes, a fifth of a second at the default 30 fps
+const TRANSITION_FRAMES: usize = 6;
+const MEDIA_FORMAT: &str = "{title}\n{artist}";
+const ALBUM_ART_SIZE: u32 = 24;
+const ALBUM_ART_MARGIN: isize = 2;
+#[cfg(feature = "stats")]
+const STATS_REFRESH_SECS: i64 = 5;
+
+#[cfg(any(target_os = "macos", target_os = "windows"))]
 fn volume_keys_debug(msg: impl AsRef<str>) {
     debug!(target: "volume-keys", "{}", msg.as_ref());
 }
 
 fn init_tracing() {
     let env_filter = tracing_subscriber::EnvFilter::try_from_default_env().unwrap_or_else(|_| {
-        tracing_subscriber::EnvFilter::new("ggoled_app=info,volume-keys=debug,media=info,mediaremote=info")
+        tracing_subscriber::EnvFilter::new(
+            "ggoled_app=info,ggoled_draw=info,volume-keys=debug,media=info,mediaremote=info",
+        )
     });
     let _ = tracing_subscriber::fmt()
         .with_env_filter(env_filter)
@@ -48,17 +99,277 @@ fn init_tracing() {
         .try_init();
 }
 
-#[derive(Serialize, Deserialize, Default, Clone, Copy)]
+#[derive(Serialize, Deserialize, Default, Clone, Copy, PartialEq)]
 enum ConfigShiftMode {
     Off,
     #[default]
     Simple,
+    Jitter,
 }
-impl ConfigShiftMode {
-    fn to_api(self) -> ShiftMode {
+
+#[derive(Serialize, Deserialize, Default, Clone, Copy)]
+enum ConfigTextOverflow {
+    #[default]
+    Scroll,
+    ScrollTogether,
+    Ellipsis,
+}
+impl ConfigTextOverflow {
+    fn to_api(self) -> TextOverflowMode {
         match self {
-            ConfigShiftMode::Off => ShiftMode::Off,
-            ConfigShiftMode::Simple => ShiftMode::Simple,
+            ConfigTextOverflow::Scroll => TextOverflowMode::Scroll,
+            ConfigTextOverflow::ScrollTogether => TextOverflowMode::ScrollTogether,
+            ConfigTextOverflow::Ellipsis => TextOverflowMode::Ellipsis,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Default, Clone, Copy)]
+enum ConfigTransitionKind {
+    Wipe,
+    Slide,
+    #[default]
+    Dissolve,
+}
+
+/// How the clock and media text change over to new text, rather than switching in a single frame.
+#[derive(Serialize, Deserialize, Clone, Copy)]
+#[serde(default)]
+struct ConfigTransition {
+    kind: ConfigTransitionKind,
+    frames: usize,
+}
+impl Default for ConfigTransition {
+    fn default() -> Self {
+        Self {
+            kind: ConfigTransitionKind::default(),
+            frames: TRANSITION_FRAMES,
+        }
+    }
+}
+impl ConfigTransition {
+    fn to_api(self) -> TransitionConfig {
+        TransitionConfig {
+            kind: match self.kind {
+                ConfigTransitionKind::Wipe => TransitionKind::Wipe,
+                ConfigTransitionKind::Slide => TransitionKind::Slide,
+                ConfigTransitionKind::Dissolve => TransitionKind::Dissolve,
+            },
+            frames: self.frames,
+        }
+    }
+}
+
+/// Where the clock goes when `layout.time` doesn't place it. `Auto` centers it, moving it to the top for media.
+#[derive(Serialize, Deserialize, Default, Clone, Copy, PartialEq)]
+enum ConfigClockPosition {
+    #[default]
+    Auto,
+    TopLeft,
+    TopCenter,
+    TopRight,
+    BottomLeft,
+    BottomCenter,
+    BottomRight,
+}
+impl ConfigClockPosition {
+    fn is_bottom(self) -> bool {
+        matches!(
+            self,
+            ConfigClockPosition::BottomLeft | ConfigClockPosition::BottomCenter | ConfigClockPosition::BottomRight
+        )
+    }
+}
+
+#[derive(Serialize, Deserialize, Default, Clone, Copy)]
+enum ConfigStatsStyle {
+    #[default]
+    Numbers,
+    Bars,
+    Graph,
+}
+
+/// What the volume keys do while they're passed through to the base station.
+#[derive(Serialize, Deserialize, Default, Clone, Copy)]
+enum ConfigVolumeKeyAction {
+    #[default]
+    BaseStationVolume,
+    /// Leave the keys to the system, so passthrough can't be turned on.
+    SystemVolume,
+    /// Use the actions in `volume_key_custom`.
+    Custom,
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy)]
+enum ConfigKeyAction {
+    VolumeUp,
+    VolumeDown,
+    ToggleMute,
+    DismissNotification,
+    Nothing,
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy)]
+#[serde(default)]
+struct ConfigVolumeKeys {
+    up: ConfigKeyAction,
+    down: ConfigKeyAction,
+    mute: ConfigKeyAction,
+}
+impl Default for ConfigVolumeKeys {
+    fn default() -> Self {
+        Self {
+            up: ConfigKeyAction::VolumeUp,
+            down: ConfigKeyAction::VolumeDown,
+            mute: ConfigKeyAction::ToggleMute,
+        }
+    }
+}
+#[cfg(any(target_os = "macos", target_os = "windows"))]
+impl ConfigVolumeKeys {
+    fn action(self, signal: VolumeKeySignal) -> ConfigKeyAction {
+        match signal {
+            VolumeKeySignal::Up => self.up,
+            VolumeKeySignal::Down => self.down,
+            VolumeKeySignal::Mute => self.mute,
+        }
+    }
+}
+
+/// Key codes and HID consumer usages that count as one of the volume keys.
+#[derive(Serialize, Deserialize, Default, Clone)]
+#[serde(default)]
+struct ConfigKeyCodes {
+    keycodes: Vec<i64>,
+    hid_usages: Vec<u32>,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(default)]
+struct ConfigVolumeKeyMap {
+    up: ConfigKeyCodes,
+    down: ConfigKeyCodes,
+    mute: ConfigKeyCodes,
+}
+impl Default for ConfigVolumeKeyMap {
+    // The media keys, the F-keys they share on Apple keyboards, and the consumer page volume usages
+    #[cfg(not(target_os = "windows"))]
+    fn default() -> Self {
+        Self {
+            up: ConfigKeyCodes {
+                keycodes: vec![72, 111],
+                hid_usages: vec![0xe9],
+            },
+            down: ConfigKeyCodes {
+                keycodes: vec![73, 103],
+                hid_usages: vec![0xea],
+            },
+            mute: ConfigKeyCodes {
+                keycodes: vec![74, 109],
+                hid_usages: vec![0xe2],
+            },
+        }
+    }
+    // The VK_VOLUME_* virtual-key codes. The keyboard hook only sees key codes, so there are no HID usages.
+    #[cfg(target_os = "windows")]
+    fn default() -> Self {
+        Self {
+            up: ConfigKeyCodes {
+                keycodes: vec![0xaf],
+                hid_usages: vec![],
+            },
+            down: ConfigKeyCodes {
+                keycodes: vec![0xae],
+                hid_usages: vec![],
+            },
+            mute: ConfigKeyCodes {
+                keycodes: vec![0xad],
+                hid_usages: vec![],
+            },
+        }
+    }
+}
+#[cfg(any(target_os = "macos", target_os = "windows"))]
+impl ConfigVolumeKeyMap {
+    fn to_api(&self) -> VolumeKeyMap {
+        let keys = [
+            (&self.up, VolumeKeySignal::Up),
+            (&self.down, VolumeKeySignal::Down),
+            (&self.mute, VolumeKeySignal::Mute),
+        ];
+        VolumeKeyMap {
+            keycodes: keys
+                .iter()
+                .flat_map(|(codes, signal)| codes.keycodes.iter().map(|code| (*code, *signal)))
+                .collect(),
+            hid_usages: keys
+                .iter()
+                .flat_map(|(codes, signal)| codes.hid_usages.iter().map(|usage| (*usage, *signal)))
+                .collect(),
+        }
+    }
+}
+
+/// Screen position of an element. Omitted coordinates are placed automatically (usually centered).
+#[derive(Serialize, Deserialize, Default, Clone, Copy)]
+#[serde(default)]
+struct ConfigPosition {
+    x: Option<isize>,
+    y: Option<isize>,
+}
+
+/// Brightness levels for day and night, switched at the start of each. Hours are 0-23 in local time.
+#[derive(Serialize, Deserialize, Clone, Copy)]
+#[serde(default)]
+struct ConfigBrightnessSchedule {
+    day: u8,
+    night: u8,
+    day_start_hour: u32,
+    night_start_hour: u32,
+}
+impl Default for ConfigBrightnessSchedule {
+    fn default() -> Self {
+        Self {
+            day: 10,
+            night: 1,
+            day_start_hour: 7,
+            night_start_hour: 22,
+        }
+    }
+}
+impl ConfigBrightnessSchedule {
+    // Night may wrap around midnight, as it usually does
+    fn is_night(&self, hour: u32) -> bool {
+        if self.night_start_hour >= self.day_start_hour {
+            hour >= self.night_start_hour || hour < self.day_start_hour
+        } else {
+            hour >= self.night_start_hour && hour < self.day_start_hour
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy)]
+#[serde(default)]
+struct ConfigLayout {
+    time: ConfigPosition,
+    media: ConfigPosition,
+    volume_notification: ConfigPosition,
+    connection_notification: ConfigPosition,
+    battery: ConfigPosition,
+    custom_text: ConfigPosition,
+}
+impl Default for ConfigLayout {
+    fn default() -> Self {
+        Self {
+            time: ConfigPosition::default(),
+            media: ConfigPosition::default(),
+            volume_notification: ConfigPosition {
+                x: Some(NOTIF_MARGIN_X),
+                y: Some(NOTIF_MARGIN_Y),
+            },
+            connection_notification: ConfigPosition::default(),
+            battery: ConfigPosition::default(),
+            custom_text: ConfigPosition::default(),
         }
     }
 }
@@ -94,61 +405,304 @@ struct WeatherCoords {
 #[serde(default)]
 struct Config {
     font: Option<ConfigFont>,
+    time_font: Option<ConfigFont>,
+    media_font: Option<ConfigFont>,
     show_time: bool,
+    time_format: String,
+    clock_position: ConfigClockPosition,
+    clock_show_seconds: bool,
+    blink_colon: bool,
     show_media: bool,
     show_media_paused: bool,
+    media_format: String,
+    show_album_art: bool,
+    text_overflow: ConfigTextOverflow,
+    transition: Option<ConfigTransition>,
     idle_timeout: bool,
+    idle_timeout_secs: usize,
     oled_shift: ConfigShiftMode,
+    oled_shift_period_secs: u64,
+    oled_shift_amplitude: isize,
+    oled_shift_jitter_radius: isize,
+    /// Screen brightness from 1 to 10, or `None` to leave the device's own setting alone.
+    brightness: Option<u8>,
+    brightness_schedule: Option<ConfigBrightnessSchedule>,
+    inversion_interval_mins: u64,
+    inversion_duration_secs: u64,
     show_notifications: bool,
+    notification_duration_secs: u64,
+    show_battery: bool,
+    custom_text: Option<String>,
+    show_custom_text: bool,
     autostart: bool,
+    wait_for_device: bool,
     pass_through_volume_keys: bool,
+    volume_key_action: ConfigVolumeKeyAction,
+    volume_key_custom: ConfigVolumeKeys,
+    volume_key_map: ConfigVolumeKeyMap,
     show_weather: bool,
     weather_provider: WeatherProvider,
     weather_units: WeatherUnits,
     weather_coords: Option<WeatherCoords>,
     weather_api_key: Option<String>,
+    show_stats: bool,
+    stats_style: ConfigStatsStyle,
+    layout: ConfigLayout,
+    slideshow_dir: Option<PathBuf>,
+    slideshow_interval_secs: u64,
+    image_threshold: u8,
+    /// Dither images instead of thresholding them, which keeps some shading in photos.
+    image_dither: bool,
+    server_addr: Option<String>,
+    /// Profile this config belongs to, `None` for the default `ggoled_app.toml`.
+    #[serde(skip)]
+    profile: Option<String>,
 }
 impl Default for Config {
     fn default() -> Self {
         Self {
             font: None,
+            time_font: None,
+            media_font: None,
             show_time: true,
+            time_format: TIME_FORMAT.to_string(),
+            clock_position: ConfigClockPosition::default(),
+            clock_show_seconds: false,
+            blink_colon: false,
             show_media: true,
             show_media_paused: false,
+            media_format: MEDIA_FORMAT.to_string(),
+            show_album_art: false,
+            text_overflow: ConfigTextOverflow::default(),
+            transition: None,
             idle_timeout: true,
+            idle_timeout_secs: IDLE_TIMEOUT_SECS,
             oled_shift: ConfigShiftMode::default(),
+            oled_shift_period_secs: OLED_SHIFT_PERIOD_SECS,
+            oled_shift_amplitude: 1,
+            oled_shift_jitter_radius: OLED_SHIFT_JITTER_RADIUS,
+            brightness: None,
+            brightness_schedule: None,
+            inversion_interval_mins: 0,
+            inversion_duration_secs: 3,
             show_notifications: true,
+            notification_duration_secs: NOTIF_DUR.as_secs(),
+            show_battery: false,
+            custom_text: None,
+            show_custom_text: true,
             autostart: false,
+            wait_for_device: false,
             pass_through_volume_keys: false,
+            volume_key_action: ConfigVolumeKeyAction::default(),
+            volume_key_custom: ConfigVolumeKeys::default(),
+            volume_key_map: ConfigVolumeKeyMap::default(),
             show_weather: false,
             weather_provider: WeatherProvider::default(),
             weather_units: WeatherUnits::default(),
             weather_coords: None,
             weather_api_key: None,
+            show_stats: false,
+            stats_style: ConfigStatsStyle::default(),
+            layout: ConfigLayout::default(),
+            slideshow_dir: None,
+            slideshow_interval_secs: 10,
+            image_threshold: IMAGE_THRESHOLD,
+            image_dither: false,
+            server_addr: None,
+            profile: None,
         }
     }
 }
 impl Config {
-    fn path() -> PathBuf {
-        directories::BaseDirs::new()
-            .unwrap()
-            .config_dir()
-            .join("ggoled_app.toml")
+    fn dir() -> PathBuf {
+        directories::BaseDirs::new().unwrap().config_dir().to_path_buf()
+    }
+    fn path_for(profile: Option<&str>) -> PathBuf {
+        match profile {
+            Some(name) => Self::dir().join(format!("ggoled_app.{name}.toml")),
+            None => Self::dir().join("ggoled_app.toml"),
+        }
+    }
+    fn path(&self) -> PathBuf {
+        Self::path_for(self.profile.as_deref())
+    }
+    // Holds the name of the active profile, and is absent while the default config is active
+    fn active_profile_path() -> PathBuf {
+        Self::dir().join("ggoled_app.profile")
+    }
+    fn active_profile() -> Option<String> {
+        let name = std::fs::read_to_string(Self::active_profile_path()).ok()?;
+        Some(name.trim().to_string()).filter(|name| !name.is_empty())
+    }
+    fn set_active_profile(profile: Option<&str>) -> anyhow::Result<()> {
+        match profile {
+            Some(name) => std::fs::write(Self::active_profile_path(), name)?,
+            None if Self::active_profile_path().exists() => std::fs::remove_file(Self::active_profile_path())?,
+            None => {}
+        }
+        Ok(())
+    }
+    // Holds the last base station volume, which the device only reports once it changes
+    fn last_volume_path() -> PathBuf {
+        Self::dir().join("ggoled_app.volume")
+    }
+    fn last_volume() -> Option<u8> {
+        let text = std::fs::read_to_string(Self::last_volume_path()).ok()?;
+        text.trim()
+            .parse()
+            .ok()
+            .filter(|volume| *volume <= BASE_STATION_VOLUME_MAX)
+    }
+    fn save_last_volume(volume: u8) -> anyhow::Result<()> {
+        std::fs::write(Self::last_volume_path(), volume.to_string())?;
+        Ok(())
+    }
+    /// Names of the profiles next to the default config, from their `ggoled_app.<name>.toml` files.
+    fn profiles() -> Vec<String> {
+        let Ok(entries) = std::fs::read_dir(Self::dir()) else {
+            return vec![];
+        };
+        let mut names: Vec<String> = entries
+            .filter_map(|entry| {
+                let file_name = entry.ok()?.file_name().into_string().ok()?;
+                let name = file_name.strip_prefix("ggoled_app.")?.strip_suffix(".toml")?;
+                (!name.is_empty()).then(|| name.to_string())
+            })
+            .collect();
+        names.sort();
+        names
     }
     pub fn save(&self) -> anyhow::Result<()> {
         let text = toml::to_string(self)?;
-        std::fs::write(Self::path(), text)?;
+        std::fs::write(self.path(), text)?;
         Ok(())
     }
     pub fn load() -> Config {
-        let Ok(text) = std::fs::read_to_string(Self::path()) else {
-            return Config::default();
-        };
-        let Ok(conf) = toml::from_str(&text) else {
-            return Config::default();
-        };
-        conf
+        let profile = Self::active_profile();
+        Self::try_load(profile.clone()).unwrap_or_else(|_| Config {
+            profile,
+            ..Config::default()
+        })
+    }
+    fn try_load(profile: Option<String>) -> anyhow::Result<Config> {
+        let text = std::fs::read_to_string(Self::path_for(profile.as_deref()))?;
+        let mut conf = toml::from_str::<Config>(&text)?;
+        if !valid_time_format(&conf.time_format) {
+            warn!(time_format = conf.time_format, "invalid time_format, using the default");
+            conf.time_format = TIME_FORMAT.to_string();
+        }
+        conf.profile = profile;
+        Ok(conf)
+    }
+    // Decodes an image, dithered or thresholded as configured
+    fn decode_image(&self, buf: &[u8]) -> anyhow::Result<ggoled_lib::Bitmap> {
+        if self.image_dither {
+            bitmap_from_memory_with_mode(buf, GrayMode::Bayer4)
+        } else {
+            bitmap_from_memory(buf, self.image_threshold)
+        }
+    }
+    // Like `decode_image`, with one frame per GIF frame
+    fn decode_frames(&self, buf: &[u8]) -> anyhow::Result<Vec<Frame>> {
+        if self.image_dither {
+            frames_from_memory_with_mode(buf, GrayMode::Bayer4)
+        } else {
+            frames_from_memory(buf, self.image_threshold)
+        }
+    }
+    fn modified(&self) -> Option<std::time::SystemTime> {
+        std::fs::metadata(self.path()).and_then(|meta| meta.modified()).ok()
+    }
+    // Turns off what this platform or build can't do
+    fn restrict_to(&mut self, capabilities: &PlatformCapabilities) {
+        self.show_media = self.show_media && capabilities.media;
+        self.show_media_paused = self.show_media_paused && capabilities.media;
+        self.idle_timeout = self.idle_timeout && capabilities.idle_timeout;
+        self.autostart = if capabilities.autostart { get_autostart() } else { false };
+        self.show_weather = self.show_weather && self.weather_coords.is_some();
+        #[cfg(not(any(target_os = "macos", target_os = "windows")))]
+        {
+            self.pass_through_volume_keys = false;
+        }
+        if matches!(self.volume_key_action, ConfigVolumeKeyAction::SystemVolume) {
+            self.pass_through_volume_keys = false;
+        }
+        #[cfg(not(feature = "stats"))]
+        {
+            self.show_stats = false;
+        }
+    }
+    fn shift_mode(&self) -> ShiftMode {
+        let period = Duration::from_secs(self.oled_shift_period_secs.max(1));
+        match self.oled_shift {
+            ConfigShiftMode::Off => ShiftMode::Off,
+            ConfigShiftMode::Simple => ShiftMode::Cycle {
+                period,
+                amplitude: self.oled_shift_amplitude,
+            },
+            ConfigShiftMode::Jitter => ShiftMode::Jitter {
+                period,
+                radius: self.oled_shift_jitter_radius,
+                seed: None,
+            },
+        }
+    }
+    fn inversion_schedule(&self) -> Option<InversionSchedule> {
+        (self.inversion_interval_mins > 0).then(|| InversionSchedule {
+            interval: Duration::from_secs(self.inversion_interval_mins * 60),
+            duration: Duration::from_secs(self.inversion_duration_secs),
+        })
+    }
+}
+
+// Formatting through `fmt::Write` reports bad strftime specifiers instead of panicking like `to_string` would
+fn valid_time_format(format: &str) -> bool {
+    let mut text = String::new();
+    write!(text, "{}", Local::now().format(format)).is_ok() && !text.trim().is_empty()
+}
+
+// Adds seconds after the minutes, unless the format already shows them
+fn time_format_with_seconds(format: &str) -> String {
+    if format.contains("%S") || format.contains("%T") || format.contains("%X") {
+        format.to_string()
+    } else {
+        format.replacen("%M", "%M:%S", 1)
+    }
+}
+
+// Fills in `{title}`, `{artist}` and `{album}` in a media format. Falls back to the default format if the
+// template uses a field the player didn't provide, so e.g. a missing artist doesn't leave a dangling separator.
+fn format_media(template: &str, media: &Media) -> String {
+    let fields = [
+        ("{title}", &media.title),
+        ("{artist}", &media.artist),
+        ("{album}", &media.album),
+    ];
+    let template = if fields
+        .iter()
+        .any(|(placeholder, value)| value.is_empty() && template.contains(placeholder))
+    {
+        MEDIA_FORMAT
+    } else {
+        template
+    };
+    fields.iter().fold(template.to_string(), |text, (placeholder, value)| {
+        text.replace(placeholder, value)
+    })
+}
+
+// Loads a notification icon, making still images blink so they catch the eye. GIFs play as they are.
+fn load_notification_icon(buf: &[u8], config: &Config) -> Vec<Frame> {
+    let mut frames = config.decode_frames(buf).expect("Failed to decode notification icon");
+    if let [frame] = frames.as_mut_slice() {
+        frame.delay = Some(NOTIF_BLINK_DUR);
+        let blank = ggoled_lib::Bitmap::new(frame.bitmap.w, frame.bitmap.h, false);
+        frames.push(Frame {
+            bitmap: Arc::new(blank),
+            delay: Some(NOTIF_BLINK_DUR),
+        });
     }
+    frames
 }
 
 fn show_error_dialog(msg: &str) {
@@ -159,13 +713,63 @@ fn show_error_dialog(msg: &str) {
         .show();
 }
 
-fn volume_from_percent(percent: u8) -> u8 {
-    (((percent as u16) * (BASE_STATION_VOLUME_MAX as u16) + 50) / 100) as u8
+fn show_warning_dialog(msg: &str) {
+    MessageDialog::new()
+        .set_level(MessageLevel::Warning)
+        .set_title("ggoled")
+        .set_description(msg)
+        .show();
 }
 
-fn volume_to_percent(volume: u8) -> u8 {
-    (((volume.min(BASE_STATION_VOLUME_MAX) as u16) * 100 + (BASE_STATION_VOLUME_MAX as u16 / 2))
-        / BASE_STATION_VOLUME_MAX as u16) as u8
+// A font that moved or broke shouldn't keep the app from starting, so warn and let the caller use the built-in one
+fn load_config_font(font: &ConfigFont) -> Option<TextRenderer> {
+    match TextRenderer::load_from_file(&font.path, font.size) {
+        Ok(texter) => Some(texter),
+        Err(err) => {
+            let path = font.path.display();
+            warn!(?err, "failed to load font {path}");
+            show_warning_dialog(&format!(
+                "Failed to load the font {path}, using the built-in font instead.\n\n{err:?}"
+            ));
+            None
+        }
+    }
+}
+
+fn load_slideshow_images(dir: &Path, width: usize, height: usize, config: &Config) -> Vec<Arc<ggoled_lib::Bitmap>> {
+    let mut paths = match std::fs::read_dir(dir) {
+        Ok(entries) => entries
+            .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+            .filter(|path| path.is_file())
+            .collect::<Vec<_>>(),
+        Err(err) => {
+            warn!(?err, "failed to read slideshow folder {}", dir.display());
+            return vec![];
+        }
+    };
+    paths.sort();
+    paths
+        .into_iter()
+        .filter_map(|path| {
+            let image = std::fs::read(&path)
+                .map_err(anyhow::Error::from)
+                .and_then(|buf| config.decode_image(&buf));
+            match image {
+                Ok(image) => {
+                    // Center every image on a full screen so they can all share one layer position
+                    let mut screen = ggoled_lib::Bitmap::new(width, height, false);
+                    let x = (width as isize - image.w as isize) / 2;
+                    let y = (height as isize - image.h as isize) / 2;
+                    screen.blit(&image, x, y, true);
+                    Some(Arc::new(screen))
+                }
+                Err(err) => {
+                    debug!(?err, "skipping slideshow file {}", path.display());
+                    None
+                }
+            }
+        })
+        .collect()
 }
 
 fn volume_icon_level(percent: u8) -> usize {
@@ -237,6 +841,25 @@ fn parse_weather_code_from_description(_desc: &str) -> Option<i32> {
     None
 }
 
+// Album art for one URL. It's fetched on a worker thread so a slow server doesn't hold up the event loop.
+enum AlbumArt {
+    Loading(Receiver<Option<Arc<ggoled_lib::Bitmap>>>),
+    // `None` if it couldn't be loaded, so it isn't fetched again every tick
+    Loaded(Option<Arc<ggoled_lib::Bitmap>>),
+}
+
+// A notification that's shown or waiting for its turn
+enum Notification {
+    Volume { text: String },
+    Connection { wireless: bool },
+}
+impl Notification {
+    // A newer notification of the same kind replaces an older one instead of queueing behind it
+    fn same_kind(&self, other: &Notification) -> bool {
+        std::mem::discriminant(self) == std::mem::discriminant(other)
+    }
+}
+
 struct TrayState {
     tray: TrayIcon,
     icon_ok: TrayIconImage,
@@ -245,10 +868,17 @@ struct TrayState {
     tm_media_check: CheckMenuItem,
     tm_media_paused_check: CheckMenuItem,
     tm_notif_check: CheckMenuItem,
+    tm_battery_check: CheckMenuItem,
+    tm_custom_text_check: CheckMenuItem,
     tm_idle_check: CheckMenuItem,
+    tm_idle_presets: Vec<(usize, CheckMenuItem)>,
+    tm_display_off_check: CheckMenuItem,
     tm_autostart_check: CheckMenuItem,
     tm_weather_check: CheckMenuItem,
+    #[cfg(feature = "stats")]
+    tm_stats_check: CheckMenuItem,
     tm_open_config: MenuItem,
+    tm_dismiss_notif: MenuItem,
     tm_volume_down: MenuItem,
     tm_volume_up: MenuItem,
     tm_volume_mute: MenuItem,
@@ -256,10 +886,15 @@ struct TrayState {
     tm_volume_50: MenuItem,
     tm_volume_75: MenuItem,
     tm_volume_100: MenuItem,
-    #[cfg(target_os = "macos")]
+    tm_play_pause: MenuItem,
+    tm_next_track: MenuItem,
+    tm_previous_track: MenuItem,
+    #[cfg(any(target_os = "macos", target_os = "windows"))]
     tm_pass_through_volume_keys_check: CheckMenuItem,
-    tm_shift_off: CheckMenuItem,
-    tm_shift_simple: CheckMenuItem,
+    tm_shift_modes: Vec<(ConfigShiftMode, CheckMenuItem)>,
+    tm_brightness_presets: Vec<(u8, CheckMenuItem)>,
+    // `None` is the default profile
+    tm_profiles: Vec<(Option<String>, CheckMenuItem)>,
     tm_quit: MenuItem,
 }
 
@@ -275,23 +910,58 @@ struct RuntimeState {
     config: Config,
     tray: TrayState,
     dev: DrawDevice,
+    time_texter: Option<TextRenderer>,
+    media_texter: Option<TextRenderer>,
     mgr: MediaControl,
     last_time: DateTime<Local>,
     last_media: Option<Media>,
+    // Album art of the last art URL
+    album_art: Option<(String, AlbumArt)>,
     time_layers: Vec<LayerId>,
     media_layers: Vec<LayerId>,
     notif_layers: Vec<LayerId>,
-    notif_expiry: DateTime<Local>,
+    notif_shown: Option<Notification>,
+    notif_queue: VecDeque<Notification>,
+    // `None` keeps the notification until it's replaced or dismissed
+    notif_expiry: Option<DateTime<Local>>,
     is_connected: Option<bool>,
+    // Last known base station volume, carried over from the previous run until the device reports one
     volume: Option<u8>,
+    // Level to restore when unmuting, `Some` while muted. The device has no mute of its own, so muting sets it to 0.
+    pre_mute_volume: Option<u8>,
+    // Volumes we set recently, whose echoes from the device shouldn't show another notification
+    volume_writes: Vec<(u8, Instant)>,
     needs_redraw: bool,
-    icon_hs_connect: Arc<ggoled_lib::Bitmap>,
-    icon_hs_disconnect: Arc<ggoled_lib::Bitmap>,
+    // Blanked from the tray until turned back on, regardless of idle time
+    display_off: bool,
+    icon_hs_connect: Vec<Frame>,
+    icon_hs_disconnect: Vec<Frame>,
+    slideshow_images: Vec<Arc<ggoled_lib::Bitmap>>,
+    slideshow_layer: Option<LayerId>,
+    // When the config file was last written, to notice edits made while running
+    config_modified: Option<std::time::SystemTime>,
+    config_checked_at: Instant,
+    // Whether the brightness schedule last applied its night level, `None` until it's first applied
+    brightness_night: Option<bool>,
+    // Headset battery level and whether it's charging, as last reported by the device
+    battery: Option<(u8, bool)>,
+    // The battery state `battery_layers` currently show
+    shown_battery: Option<(u8, bool)>,
+    battery_layers: Vec<LayerId>,
+    // The custom text `custom_text_layers` currently show
+    shown_custom_text: Option<String>,
+    custom_text_layers: Vec<LayerId>,
     weather: WeatherState,
     weather_cache: draconis::CacheManager,
     weather_plugin: Option<draconis::Plugin>,
-    #[cfg(target_os = "macos")]
-    volume_key_rx: Option<std::sync::mpsc::Receiver<VolumeKeySignal>>,
+    #[cfg(feature = "stats")]
+    stats: stats::SystemStats,
+    #[cfg(feature = "stats")]
+    stats_layers: Vec<LayerId>,
+    #[cfg(feature = "stats")]
+    last_stats: Option<DateTime<Local>>,
+    #[cfg(any(target_os = "macos", target_os = "windows"))]
+    volume_key_listener: Option<VolumeKeyListener>,
 }
 
 enum UserEvent {
@@ -300,29 +970,51 @@ enum UserEvent {
 }
 
 impl RuntimeState {
-    fn new(config: Config, capabilities: PlatformCapabilities, tray: TrayState) -> anyhow::Result<RuntimeState> {
+    fn new(
+        config: Config,
+        capabilities: PlatformCapabilities,
+        tray: TrayState,
+        device: Device,
+    ) -> anyhow::Result<RuntimeState> {
         #[allow(unused_mut)]
         let mut config = config;
         #[allow(unused_mut)]
         let mut tray = tray;
 
-        let icon_hs_connect =
-            Arc::new(bitmap_from_memory(include_bytes!("../assets/headset_connected.png"), 0x80).unwrap());
-        let icon_hs_disconnect =
-            Arc::new(bitmap_from_memory(include_bytes!("../assets/headset_disconnected.png"), 0x80).unwrap());
+        let icon_hs_connect = load_notification_icon(include_bytes!("../assets/headset_connected.png"), &config);
+        let icon_hs_disconnect = load_notification_icon(include_bytes!("../assets/headset_disconnected.png"), &config);
+
+        let load_font = |font: &Option<ConfigFont>| font.as_ref().and_then(load_config_font);
+        let texter = load_font(&config.font).unwrap_or_else(TextRenderer::new_merged);
+        let mut builder = DrawDevice::builder(device)
+            .fps(30)
+            .texter(texter)
+            .shift_mode(config.shift_mode())
+            .inversion_schedule(config.inversion_schedule())
+            .playing();
+        if let Some(brightness) = config.brightness {
+            builder = builder.brightness(brightness);
+        }
+        let mut dev = builder.build();
+        let time_texter = load_font(&config.time_font);
+        let media_texter = load_font(&config.media_font);
+        let slideshow_images = config
+            .slideshow_dir
+            .as_ref()
+            .map(|dir| load_slideshow_images(dir, dev.width(), dev.height(), &config))
+            .unwrap_or_default();
 
-        let mut dev = DrawDevice::new(Device::connect()?, 30);
-        if let Some(font) = &config.font {
-            dev.texter = TextRenderer::load_from_file(&font.path, font.size)?;
-        } else {
-            dev.texter = TextRenderer::new_merged();
+        #[cfg(feature = "server")]
+        if let Some(addr) = &config.server_addr {
+            if let Err(err) = server::spawn(addr, dev.frame_snapshot()) {
+                warn!(?err, "failed to start screen server on {addr}");
+            }
         }
 
-        dev.set_shift_mode(config.oled_shift.to_api());
-        dev.play();
+        dev.watch_device();
 
-        #[cfg(target_os = "macos")]
-        let volume_key_rx = if config.pass_through_volume_keys {
+        #[cfg(any(target_os = "macos", target_os = "windows"))]
+        let volume_key_listener = if config.pass_through_volume_keys {
             volume_keys_debug("startup: passthrough enabled in config; checking accessibility permission");
             if !ensure_accessibility_permission(false) {
                 config.pass_through_volume_keys = false;
@@ -332,8 +1024,8 @@ impl RuntimeState {
                 None
             } else {
                 volume_keys_debug("startup: permission granted; starting key listener");
-                let rx = start_volume_key_listener();
-                if rx.is_none() {
+                let listener = start_volume_key_listener(config.volume_key_map.to_api());
+                if listener.is_none() {
                     config.pass_through_volume_keys = false;
                     tray.tm_pass_through_volume_keys_check.set_checked(false);
                     warn!("failed to start media volume key passthrough listener");
@@ -341,21 +1033,20 @@ impl RuntimeState {
                 } else {
                     volume_keys_debug("startup: listener started");
                 }
-                rx
+                listener
             }
         } else {
             volume_keys_debug("startup: passthrough disabled in config");
             None
         };
 
-        let weather_plugin = if config.show_weather && config.weather_coords.is_some() {
+        let weather_plugin = if let Some(coords) = config.weather_coords.as_ref().filter(|_| config.show_weather) {
             let count = draconis::init_static_plugins();
             tracing::debug!("Initialized {} static plugins", count);
             draconis::initialize_plugin_manager();
 
             match draconis::Plugin::new("WeatherPlugin") {
                 Ok(mut plugin) => {
-                    let coords = config.weather_coords.as_ref().unwrap();
                     let provider_str = match config.weather_provider {
                         WeatherProvider::OpenMeteo => "openmeteo",
                         WeatherProvider::MetNo => "metno",
@@ -416,24 +1107,43 @@ lon = {}"#,
         };
 
         let weather_units = config.weather_units;
+        let config_modified = config.modified();
 
         Ok(RuntimeState {
             capabilities,
             config,
             tray,
             dev,
+            time_texter,
+            media_texter,
             mgr: MediaControl::new(),
             last_time: Local::now() - TimeDelta::seconds(1),
             last_media: None,
+            album_art: None,
             time_layers: vec![],
             media_layers: vec![],
             notif_layers: vec![],
-            notif_expiry: Local::now(),
+            notif_shown: None,
+            notif_queue: VecDeque::new(),
+            notif_expiry: None,
             is_connected: None,
-            volume: None,
+            volume: Config::last_volume(),
+            pre_mute_volume: None,
+            volume_writes: vec![],
             needs_redraw: false,
+            display_off: false,
             icon_hs_connect,
             icon_hs_disconnect,
+            slideshow_images,
+            slideshow_layer: None,
+            config_modified,
+            config_checked_at: Instant::now(),
+            brightness_night: None,
+            battery: None,
+            shown_battery: None,
+            battery_layers: vec![],
+            shown_custom_text: None,
+            custom_text_layers: vec![],
             weather: WeatherState {
                 temperature: None,
                 weather_code: None,
@@ -442,78 +1152,498 @@ lon = {}"#,
             },
             weather_cache: draconis::CacheManager::new(),
             weather_plugin,
-            #[cfg(target_os = "macos")]
-            volume_key_rx,
+            #[cfg(feature = "stats")]
+            stats: stats::SystemStats::new(),
+            #[cfg(feature = "stats")]
+            stats_layers: vec![],
+            #[cfg(feature = "stats")]
+            last_stats: None,
+            #[cfg(any(target_os = "macos", target_os = "windows"))]
+            volume_key_listener,
         })
     }
 
-    fn save_config(&self) -> anyhow::Result<()> {
-        self.config.save()
+    fn save_config(&mut self) -> anyhow::Result<()> {
+        self.config.save()?;
+        // Our own write shouldn't count as an edit to reload
+        self.config_modified = self.config.modified();
+        Ok(())
+    }
+
+    fn reload_config_if_changed(&mut self) {
+        if self.config_checked_at.elapsed() < CONFIG_CHECK_PERIOD {
+            return;
+        }
+        self.config_checked_at = Instant::now();
+        let modified = self.config.modified();
+        if modified == self.config_modified {
+            return;
+        }
+        self.config_modified = modified;
+        match Config::try_load(self.config.profile.clone()) {
+            Ok(mut config) => {
+                info!("config file changed, reloading");
+                config.restrict_to(&self.capabilities);
+                self.apply_config(config);
+            }
+            Err(err) => warn!(?err, "ignoring invalid config file"),
+        }
+    }
+
+    fn switch_profile(&mut self, profile: Option<String>) {
+        // A profile that hasn't been saved yet starts out with the defaults, like at startup
+        let loaded = Config::try_load(profile.clone()).or_else(|err| match err.downcast_ref::<std::io::Error>() {
+            Some(io_err) if io_err.kind() == std::io::ErrorKind::NotFound => Ok(Config {
+                profile: profile.clone(),
+                ..Config::default()
+            }),
+            _ => Err(err),
+        });
+        match loaded {
+            Ok(mut config) => {
+                info!(?profile, "switching profile");
+                if let Err(err) = Config::set_active_profile(profile.as_deref()) {
+                    warn!(?err, "failed to remember the active profile");
+                }
+                config.restrict_to(&self.capabilities);
+                self.apply_config(config);
+                self.config_modified = self.config.modified();
+            }
+            Err(err) => {
+                let path = Config::path_for(profile.as_deref());
+                show_error_dialog(&format!("Failed to load the profile {}:\n\n{err:?}", path.display()));
+            }
+        }
+        for (profile, item) in &self.tray.tm_profiles {
+            item.set_checked(*profile == self.config.profile);
+        }
+    }
+
+    // The slideshow, notification icons, weather, screen server and volume key listener are only set up at startup,
+    // so changes to those still need a restart
+    fn apply_config(&mut self, mut config: Config) {
+        config.pass_through_volume_keys = self.config.pass_through_volume_keys;
+
+        let load_font = |font: &Option<ConfigFont>| font.as_ref().and_then(load_config_font);
+        self.dev
+            .set_texter(load_font(&config.font).unwrap_or_else(TextRenderer::new_merged));
+        self.time_texter = load_font(&config.time_font);
+        self.media_texter = load_font(&config.media_font);
+        self.dev.set_shift_mode(config.shift_mode());
+        self.dev.set_inversion_schedule(config.inversion_schedule());
+        if config.brightness != self.config.brightness {
+            if let Some(brightness) = config.brightness {
+                self.dev.set_brightness(brightness);
+            }
+        }
+        self.brightness_night = None;
+
+        let tray = &self.tray;
+        tray.tm_time_check.set_checked(config.show_time);
+        tray.tm_media_check.set_checked(config.show_media);
+        tray.tm_media_paused_check.set_checked(config.show_media_paused);
+        tray.tm_notif_check.set_checked(config.show_notifications);
+        tray.tm_battery_check.set_checked(config.show_battery);
+        tray.tm_custom_text_check.set_checked(config.show_custom_text);
+        tray.tm_custom_text_check.set_enabled(config.custom_text.is_some());
+        tray.tm_idle_check.set_checked(config.idle_timeout);
+        tray.tm_autostart_check.set_checked(config.autostart);
+        tray.tm_weather_check.set_checked(config.show_weather);
+        #[cfg(feature = "stats")]
+        tray.tm_stats_check.set_checked(config.show_stats);
+        for (mode, item) in &tray.tm_shift_modes {
+            item.set_checked(config.oled_shift == *mode);
+        }
+        for (secs, item) in &tray.tm_idle_presets {
+            item.set_checked(config.idle_timeout_secs == *secs);
+        }
+        for (level, item) in &tray.tm_brightness_presets {
+            item.set_checked(config.brightness == Some(*level));
+        }
+
+        // Everything is laid out again with the new settings on the next tick
+        let old_layers = [
+            std::mem::take(&mut self.media_layers),
+            std::mem::take(&mut self.battery_layers),
+            std::mem::take(&mut self.custom_text_layers),
+        ]
+        .concat();
+        self.dev.remove_layers(&old_layers);
+        self.last_media = None;
+        self.shown_battery = None;
+        self.shown_custom_text = None;
+        #[cfg(feature = "stats")]
+        {
+            self.dev.remove_layers(&std::mem::take(&mut self.stats_layers));
+            self.last_stats = None;
+        }
+        self.config = config;
+        self.needs_redraw = true;
     }
 
     fn tick_duration(&self) -> Duration {
         if self.config.pass_through_volume_keys {
             TICK_DUR_FAST
+        } else if self.is_active() {
+            TICK_DUR_ACTIVE
         } else {
             TICK_DUR_NORMAL
         }
     }
 
+    // Whether something on screen changes on its own soon, so ticks should be frequent enough to keep up
+    fn is_active(&self) -> bool {
+        self.last_media.is_some() || !self.notif_layers.is_empty() || self.config.blink_colon
+    }
+
+    // Only switches brightness when day turns to night or back, so a level picked from the tray lasts until then
+    fn apply_brightness_schedule(&mut self, time: &DateTime<Local>) {
+        let Some(schedule) = self.config.brightness_schedule else {
+            return;
+        };
+        let night = schedule.is_night(time.hour());
+        if self.brightness_night == Some(night) {
+            return;
+        }
+        self.brightness_night = Some(night);
+        self.dev
+            .set_brightness(if night { schedule.night } else { schedule.day });
+    }
+
+    // Removes every layer and forgets what they showed, so the next tick draws everything again
+    fn clear_all_layers(&mut self) {
+        self.dev.clear_layers();
+        self.last_media = None;
+        self.slideshow_layer = None;
+        self.shown_battery = None;
+        self.battery_layers.clear();
+        self.shown_custom_text = None;
+        self.custom_text_layers.clear();
+        // Without this a notification that never expires would keep `is_active` ticking while the screen is dark
+        self.notif_layers.clear();
+        self.notif_shown = None;
+        self.notif_queue.clear();
+        self.notif_expiry = None;
+        #[cfg(feature = "stats")]
+        {
+            self.stats_layers.clear();
+            self.last_stats = None;
+        }
+    }
+
+    // Shows the headset battery in the top right corner, like "75%", with a "+" in front while charging
+    fn update_battery(&mut self) {
+        let battery = self.battery.filter(|_| self.config.show_battery);
+        if battery == self.shown_battery {
+            return;
+        }
+        self.shown_battery = battery;
+        let text = battery.map(|(level, charging)| {
+            let percent = level as usize * 100 / BATTERY_LEVEL_MAX as usize;
+            format!("{}{percent}%", if charging { "+" } else { "" })
+        });
+        let pos = self.config.layout.battery;
+        let x = match &text {
+            Some(text) => pos
+                .x
+                .unwrap_or(self.dev.width() as isize - self.dev.measure_line_widths(text)[0] as isize),
+            None => 0,
+        };
+        let old_layers = std::mem::take(&mut self.battery_layers);
+        self.battery_layers = self.dev.transact_layers(|txn| {
+            txn.remove_layers(&old_layers);
+            match &text {
+                Some(text) => {
+                    txn.add_text_with_mode(text, Some(x), Some(pos.y.unwrap_or(0)), true, TextOverflowMode::Clip)
+                }
+                None => vec![],
+            }
+        });
+    }
+
+    // Shows the custom text, centered on the bottom line unless positioned in the layout
+    fn update_custom_text(&mut self) {
+        let text = self.config.custom_text.clone().filter(|_| self.config.show_custom_text);
+        if text == self.shown_custom_text {
+            return;
+        }
+        let pos = self.config.layout.custom_text;
+        let y = pos
+            .y
+            .unwrap_or(self.dev.height() as isize - self.dev.font_line_height() as isize);
+        let old_layers = std::mem::take(&mut self.custom_text_layers);
+        self.custom_text_layers = self.dev.transact_layers(|txn| {
+            txn.remove_layers(&old_layers);
+            match &text {
+                Some(text) => txn.add_text(text, pos.x, Some(y)),
+                None => vec![],
+            }
+        });
+        self.shown_custom_text = text;
+    }
+
+    fn stats_height(&self) -> isize {
+        #[cfg(feature = "stats")]
+        if self.config.show_stats {
+            return match self.config.stats_style {
+                ConfigStatsStyle::Numbers => self.dev.font_line_height() as isize,
+                ConfigStatsStyle::Bars => stats::BARS_HEIGHT as isize,
+                ConfigStatsStyle::Graph => stats::GRAPH_HEIGHT as isize,
+            };
+        }
+        0
+    }
+
+    #[cfg(feature = "stats")]
+    fn update_stats(&mut self, time: DateTime<Local>) {
+        if !self.config.show_stats {
+            if !self.stats_layers.is_empty() {
+                self.dev.remove_layers(&self.stats_layers);
+                self.stats_layers.clear();
+            }
+            self.last_stats = None;
+            return;
+        }
+        let due = self
+            .last_stats
+            .is_none_or(|last| time.signed_duration_since(last).num_seconds() >= STATS_REFRESH_SECS);
+        if !due {
+            return;
+        }
+        self.last_stats = Some(time);
+
+        let sample = self.stats.sample();
+        let (width, height) = (self.dev.width(), self.dev.height());
+        let old_layers = std::mem::take(&mut self.stats_layers);
+        match self.config.stats_style {
+            ConfigStatsStyle::Numbers => {
+                let text = format!("CPU {:.0}% RAM {:.0}%", sample.cpu, sample.ram);
+                let y = (height - self.dev.font_line_height()) as isize;
+                self.stats_layers = self.dev.transact_layers(|txn| {
+                    txn.remove_layers(&old_layers);
+                    txn.add_text_with_mode(&text, None, Some(y), true, TextOverflowMode::Clip)
+                });
+            }
+            ConfigStatsStyle::Bars => {
+                let bitmap = Arc::new(stats::render_bars(width, &sample));
+                self.stats_layers = self.dev.transact_layers(|txn| {
+                    txn.remove_layers(&old_layers);
+                    vec![txn.add_layer(DrawLayer::Image {
+                        bitmap,
+                        x: 0,
+                        y: (height - stats::BARS_HEIGHT) as isize,
+                    })]
+                });
+            }
+            ConfigStatsStyle::Graph => {
+                let series = match old_layers.first() {
+                    Some(&id) => id,
+                    None => self.dev.add_layer(DrawLayer::Series {
+                        values: vec![],
+                        x: 0,
+                        y: (height - stats::GRAPH_HEIGHT) as isize,
+                        w: width,
+                        h: stats::GRAPH_HEIGHT,
+                    }),
+                };
+                self.dev.push_series_sample(series, sample.cpu);
+                self.stats_layers = vec![series];
+            }
+        }
+    }
+
+    fn start_notification_timer(&mut self) {
+        let secs = self.config.notification_duration_secs;
+        self.notif_expiry = (secs > 0).then(|| Local::now() + TimeDelta::seconds(secs as i64));
+    }
+
+    // Removes the shown notification, moving on to the next queued one
     fn clear_notification(&mut self) {
         if !self.notif_layers.is_empty() {
             self.dev.remove_layers(&self.notif_layers);
             self.notif_layers.clear();
         }
+        self.notif_shown = None;
+        if let Some(next) = self.notif_queue.pop_front() {
+            self.show_notification(next);
+        }
     }
 
-    fn show_volume_notification(&mut self, volume: u8) {
+    // Shows a notification right away if nothing else is showing, or it updates the one that is. Otherwise it waits
+    // its turn, unless notifications stay up until replaced, in which case it replaces the shown one.
+    fn notify(&mut self, notif: Notification) {
         if !self.config.show_notifications {
             return;
         }
-        let percent = volume_to_percent(volume);
-        let icon_char = volume_icon_char(volume_icon_level(percent));
-        let text = format!("{} {}%", icon_char, percent);
-        self.clear_notification();
-        self.notif_layers.extend(
-            self.dev
-                .add_text_no_shift(&text, Some(NOTIF_MARGIN_X), Some(NOTIF_MARGIN_Y)),
-        );
-        self.notif_expiry = Local::now() + TimeDelta::from_std(NOTIF_DUR).unwrap();
+        let wait =
+            self.notif_expiry.is_some() && self.notif_shown.as_ref().is_some_and(|shown| !shown.same_kind(&notif));
+        if !wait {
+            self.show_notification(notif);
+        } else if let Some(queued) = self.notif_queue.iter_mut().find(|queued| queued.same_kind(&notif)) {
+            *queued = notif;
+        } else {
+            if self.notif_queue.len() >= NOTIF_QUEUE_MAX {
+                self.notif_queue.pop_front();
+            }
+            self.notif_queue.push_back(notif);
+        }
+    }
+
+    fn show_notification(&mut self, notif: Notification) {
+        let old_layers = std::mem::take(&mut self.notif_layers);
+        self.notif_layers = match &notif {
+            Notification::Volume { text } => {
+                let pos = self.config.layout.volume_notification;
+                self.dev
+                    .replace_layers(&old_layers, |txn| txn.add_text_no_shift(text, pos.x, pos.y))
+            }
+            Notification::Connection { wireless } => {
+                let frames = (if *wireless {
+                    &self.icon_hs_connect
+                } else {
+                    &self.icon_hs_disconnect
+                })
+                .clone();
+                let pos = self.config.layout.connection_notification;
+                let x = pos.x.unwrap_or_else(|| self.dev.center_x(frames[0].bitmap.w));
+                let y = pos.y.unwrap_or_else(|| self.dev.center_y(frames[0].bitmap.h));
+                vec![self.dev.replace_layers(&old_layers, |txn| {
+                    txn.add_layer(DrawLayer::Animation {
+                        frames,
+                        x,
+                        y,
+                        follow_fps: false,
+                    })
+                })]
+            }
+        };
+        self.notif_shown = Some(notif);
+        self.start_notification_timer();
         self.needs_redraw = true;
     }
 
+    fn show_volume_notification(&mut self, volume: u8) {
+        // While muted, show the level that unmuting restores next to the mute icon
+        let (icon_level, percent) = match self.pre_mute_volume {
+            Some(level) => (0, volume_to_percent(level)),
+            None => {
+                let percent = volume_to_percent(volume);
+                (volume_icon_level(percent), percent)
+            }
+        };
+        let text = format!("{} {}%", volume_icon_char(icon_level), percent);
+        self.notify(Notification::Volume { text });
+    }
+
+    // Whether a volume reported by the device is just our own write coming back. Consumes the matching write,
+    // along with any earlier ones since the device reports them in order.
+    fn is_volume_echo(&mut self, volume: u8) -> bool {
+        let now = Instant::now();
+        self.volume_writes
+            .retain(|(_, at)| now.duration_since(*at) < VOLUME_ECHO_WINDOW);
+        match self.volume_writes.iter().position(|(written, _)| *written == volume) {
+            Some(i) => {
+                self.volume_writes.drain(..=i);
+                true
+            }
+            None => false,
+        }
+    }
+
+    // Keeps the volume for the next start as well, so relative changes don't start from a guess
+    fn remember_volume(&mut self, volume: u8) {
+        self.volume = Some(volume);
+        if let Err(err) = Config::save_last_volume(volume) {
+            warn!(%err, "failed to save the volume");
+        }
+    }
+
+    // Volume to base relative changes on, which is the level from before muting while muted
+    fn current_volume(&self) -> u8 {
+        self.pre_mute_volume
+            .or(self.volume)
+            .unwrap_or(BASE_STATION_VOLUME_MAX / 2)
+    }
+
+    fn set_pre_mute_volume(&mut self, pre_mute_volume: Option<u8>) {
+        self.pre_mute_volume = pre_mute_volume;
+        self.tray
+            .tm_volume_mute
+            .set_text(if pre_mute_volume.is_some() { "Unmute" } else { "Mute" });
+    }
+
+    fn toggle_mute(&mut self) {
+        match self.pre_mute_volume {
+            Some(level) => self.set_base_station_volume(level),
+            None => {
+                let level = self.current_volume();
+                self.set_pre_mute_volume(Some(level));
+                self.write_base_station_volume(0);
+            }
+        }
+    }
+
     fn set_base_station_volume(&mut self, next: u8) {
+        self.set_pre_mute_volume(None);
+        self.write_base_station_volume(next);
+    }
+
+    fn write_base_station_volume(&mut self, next: u8) {
         let next = next.min(BASE_STATION_VOLUME_MAX);
         let changed = self.volume != Some(next);
         self.dev.set_volume(next);
-        self.volume = Some(next);
+        self.volume_writes.push((next, Instant::now()));
         if changed {
+            self.remember_volume(next);
             self.show_volume_notification(next);
             self.needs_redraw = true;
         }
     }
 
-    #[cfg(target_os = "macos")]
+    #[cfg(any(target_os = "macos", target_os = "windows"))]
     fn ensure_volume_key_listener(&mut self) {
-        if self.volume_key_rx.is_none() && ensure_accessibility_permission(false) {
+        if self.volume_key_listener.is_none() && ensure_accessibility_permission(false) {
             volume_keys_debug("ensure listener: trying to start listener");
-            self.volume_key_rx = start_volume_key_listener();
+            self.volume_key_listener = start_volume_key_listener(self.config.volume_key_map.to_api());
             volume_keys_debug(format!(
                 "ensure listener: listener present={}",
-                self.volume_key_rx.is_some()
+                self.volume_key_listener.is_some()
             ));
         }
     }
 
-    #[cfg(target_os = "macos")]
+    #[cfg(any(target_os = "macos", target_os = "windows"))]
     fn handle_volume_key_signal(&mut self, signal: VolumeKeySignal) {
-        let current = self.volume.unwrap_or(BASE_STATION_VOLUME_MAX / 2);
-        let next = match signal {
-            VolumeKeySignal::Up => current
+        let keys = match self.config.volume_key_action {
+            ConfigVolumeKeyAction::Custom => self.config.volume_key_custom,
+            ConfigVolumeKeyAction::BaseStationVolume | ConfigVolumeKeyAction::SystemVolume => {
+                ConfigVolumeKeys::default()
+            }
+        };
+        let current = self.current_volume();
+        let next = match keys.action(signal) {
+            ConfigKeyAction::VolumeUp => current
                 .saturating_add(BASE_STATION_VOLUME_STEP)
                 .min(BASE_STATION_VOLUME_MAX),
-            VolumeKeySignal::Down => current.saturating_sub(BASE_STATION_VOLUME_STEP),
-            VolumeKeySignal::Mute => 0,
+            ConfigKeyAction::VolumeDown => current.saturating_sub(BASE_STATION_VOLUME_STEP),
+            ConfigKeyAction::ToggleMute => {
+                volume_keys_debug(format!(
+                    "handle signal: {:?}, muted={}",
+                    signal,
+                    self.pre_mute_volume.is_some()
+                ));
+                self.toggle_mute();
+                return;
+            }
+            ConfigKeyAction::DismissNotification => {
+                volume_keys_debug(format!("handle signal: {signal:?}, dismissing notification"));
+                self.clear_notification();
+                return;
+            }
+            ConfigKeyAction::Nothing => {
+                volume_keys_debug(format!("handle signal: {signal:?}, ignored"));
+                return;
+            }
         };
         volume_keys_debug(format!(
             "handle signal: {:?}, current={} -> next={}",
@@ -533,6 +1663,8 @@ lon = {}"#,
             || event.id == self.tray.tm_media_check.id()
             || event.id == self.tray.tm_media_paused_check.id()
             || event.id == self.tray.tm_notif_check.id()
+            || event.id == self.tray.tm_battery_check.id()
+            || event.id == self.tray.tm_custom_text_check.id()
             || event.id == self.tray.tm_idle_check.id()
             || event.id == self.tray.tm_weather_check.id()
         {
@@ -540,34 +1672,91 @@ lon = {}"#,
             self.config.show_media = self.capabilities.media && self.tray.tm_media_check.is_checked();
             self.config.show_media_paused = self.capabilities.media && self.tray.tm_media_paused_check.is_checked();
             self.config.show_notifications = self.tray.tm_notif_check.is_checked();
+            self.config.show_battery = self.tray.tm_battery_check.is_checked();
+            self.config.show_custom_text = self.tray.tm_custom_text_check.is_checked();
             self.config.idle_timeout = self.capabilities.idle_timeout && self.tray.tm_idle_check.is_checked();
             self.config.show_weather = self.tray.tm_weather_check.is_checked();
             config_updated = true;
         }
 
-        if event.id == self.tray.tm_shift_off.id() {
-            self.config.oled_shift = ConfigShiftMode::Off;
-            self.tray.tm_shift_off.set_checked(true);
-            self.tray.tm_shift_simple.set_checked(false);
-            self.dev.set_shift_mode(self.config.oled_shift.to_api());
+        #[cfg(feature = "stats")]
+        if event.id == self.tray.tm_stats_check.id() {
+            self.config.show_stats = self.tray.tm_stats_check.is_checked();
             config_updated = true;
         }
 
-        if event.id == self.tray.tm_shift_simple.id() {
-            self.config.oled_shift = ConfigShiftMode::Simple;
-            self.tray.tm_shift_off.set_checked(false);
-            self.tray.tm_shift_simple.set_checked(true);
-            self.dev.set_shift_mode(self.config.oled_shift.to_api());
+        if let Some(mode) = self
+            .tray
+            .tm_shift_modes
+            .iter()
+            .find(|(_, item)| event.id == item.id())
+            .map(|(mode, _)| *mode)
+        {
+            self.config.oled_shift = mode;
+            for (other, item) in &self.tray.tm_shift_modes {
+                item.set_checked(*other == mode);
+            }
+            self.dev.set_shift_mode(self.config.shift_mode());
             config_updated = true;
         }
 
+        if let Some(brightness) = self
+            .tray
+            .tm_brightness_presets
+            .iter()
+            .find(|(_, item)| event.id == item.id())
+            .map(|(brightness, _)| *brightness)
+        {
+            self.config.brightness = Some(brightness);
+            for (preset, item) in &self.tray.tm_brightness_presets {
+                item.set_checked(*preset == brightness);
+            }
+            self.dev.set_brightness(brightness);
+            config_updated = true;
+        }
+
+        if let Some(profile) = self
+            .tray
+            .tm_profiles
+            .iter()
+            .find(|(_, item)| event.id == item.id())
+            .map(|(profile, _)| profile.clone())
+        {
+            self.switch_profile(profile);
+        }
+
+        if let Some(secs) = self
+            .tray
+            .tm_idle_presets
+            .iter()
+            .find(|(_, item)| event.id == item.id())
+            .map(|(secs, _)| *secs)
+        {
+            self.config.idle_timeout_secs = secs;
+            for (preset, item) in &self.tray.tm_idle_presets {
+                item.set_checked(*preset == secs);
+            }
+            config_updated = true;
+        }
+
+        if event.id == self.tray.tm_display_off_check.id() {
+            self.display_off = self.tray.tm_display_off_check.is_checked();
+            if self.display_off {
+                self.clear_all_layers();
+                self.dev.blank();
+            } else {
+                self.dev.play();
+                self.needs_redraw = true;
+            }
+        }
+
         if event.id == self.tray.tm_autostart_check.id() && self.capabilities.autostart {
             self.config.autostart = self.tray.tm_autostart_check.is_checked();
             set_autostart(self.config.autostart);
             config_updated = true;
         }
 
-        #[cfg(target_os = "macos")]
+        #[cfg(any(target_os = "macos", target_os = "windows"))]
         if event.id == self.tray.tm_pass_through_volume_keys_check.id() {
             self.config.pass_through_volume_keys = self.tray.tm_pass_through_volume_keys_check.is_checked();
             volume_keys_debug(format!(
@@ -583,7 +1772,7 @@ lon = {}"#,
                     return false;
                 }
                 self.ensure_volume_key_listener();
-                if self.volume_key_rx.is_none() {
+                if self.volume_key_listener.is_none() {
                     self.config.pass_through_volume_keys = false;
                     self.tray.tm_pass_through_volume_keys_check.set_checked(false);
                     show_error_dialog(
@@ -593,27 +1782,29 @@ lon = {}"#,
                 } else {
                     volume_keys_debug("menu: listener started");
                 }
+            } else if self.volume_key_listener.take().is_some() {
+                // Stops the event taps, so the keys control the macOS volume again
+                volume_keys_debug("menu: listener stopped");
             }
             config_updated = true;
         }
 
-        if event.id == self.tray.tm_volume_down.id()
+        if event.id == self.tray.tm_volume_mute.id() {
+            self.toggle_mute();
+        } else if event.id == self.tray.tm_volume_down.id()
             || event.id == self.tray.tm_volume_up.id()
-            || event.id == self.tray.tm_volume_mute.id()
             || event.id == self.tray.tm_volume_25.id()
             || event.id == self.tray.tm_volume_50.id()
             || event.id == self.tray.tm_volume_75.id()
             || event.id == self.tray.tm_volume_100.id()
         {
-            let current = self.volume.unwrap_or(BASE_STATION_VOLUME_MAX / 2);
+            let current = self.current_volume();
             let next = if event.id == self.tray.tm_volume_down.id() {
                 current.saturating_sub(BASE_STATION_VOLUME_STEP)
             } else if event.id == self.tray.tm_volume_up.id() {
                 current
                     .saturating_add(BASE_STATION_VOLUME_STEP)
                     .min(BASE_STATION_VOLUME_MAX)
-            } else if event.id == self.tray.tm_volume_mute.id() {
-                0
             } else if event.id == self.tray.tm_volume_25.id() {
                 volume_from_percent(25)
             } else if event.id == self.tray.tm_volume_50.id() {
@@ -626,8 +1817,26 @@ lon = {}"#,
             self.set_base_station_volume(next);
         }
 
+        let playback = if event.id == self.tray.tm_play_pause.id() {
+            Some(("play/pause", self.mgr.play_pause()))
+        } else if event.id == self.tray.tm_next_track.id() {
+            Some(("next", self.mgr.next()))
+        } else if event.id == self.tray.tm_previous_track.id() {
+            Some(("previous", self.mgr.previous()))
+        } else {
+            None
+        };
+        if let Some((command, Err(err))) = playback {
+            warn!(?err, command, "failed to control media playback");
+        }
+
+        if event.id == self.tray.tm_dismiss_notif.id() {
+            self.clear_notification();
+            self.needs_redraw = true;
+        }
+
         if event.id == self.tray.tm_open_config.id() {
-            let config_path = Config::path();
+            let config_path = self.config.path();
             #[cfg(target_os = "windows")]
             {
                 std::process::Command::new("explorer")
@@ -663,15 +1872,14 @@ lon = {}"#,
     }
 
     fn tick(&mut self) {
+        self.reload_config_if_changed();
         let mut force_redraw = std::mem::take(&mut self.needs_redraw);
 
-        #[cfg(target_os = "macos")]
+        #[cfg(any(target_os = "macos", target_os = "windows"))]
         {
             let mut signals = vec![];
-            if let Some(rx) = &self.volume_key_rx {
-                while let Ok(signal) = rx.try_recv() {
-                    signals.push(signal);
-                }
+            if let Some(listener) = &self.volume_key_listener {
+                signals.extend(listener.signals());
             }
             if !signals.is_empty() {
                 volume_keys_debug(format!(
@@ -693,62 +1901,71 @@ lon = {}"#,
         while let Some(event) = self.dev.try_event() {
             debug!(?event, "draw event");
             match event {
+                DrawEvent::DeviceError(err) => warn!(%err, "device error"),
                 DrawEvent::DeviceDisconnected => _ = self.tray.tray.set_icon(Some(self.tray.icon_error.clone())),
                 DrawEvent::DeviceReconnected => _ = self.tray.tray.set_icon(Some(self.tray.icon_ok.clone())),
                 #[allow(clippy::single_match)]
                 DrawEvent::DeviceEvent(event) => match event {
                     ggoled_lib::DeviceEvent::Volume { volume } => {
                         let volume = volume.min(BASE_STATION_VOLUME_MAX);
+                        if self.is_volume_echo(volume) {
+                            continue;
+                        }
                         let changed = self.volume != Some(volume);
-                        self.volume = Some(volume);
                         if changed {
+                            self.remember_volume(volume);
+                            // Changing the volume on the device itself ends our mute
+                            if self.pre_mute_volume.is_some() {
+                                self.set_pre_mute_volume(None);
+                            }
                             self.show_volume_notification(volume);
                             force_redraw = true;
                         }
                     }
-                    ggoled_lib::DeviceEvent::HeadsetConnection { wireless, .. } => {
-                        if Some(wireless) != self.is_connected {
-                            self.is_connected = Some(wireless);
-                            if self.config.show_notifications {
-                                self.clear_notification();
-                                self.notif_layers.push(
-                                    self.dev.add_layer(ggoled_draw::DrawLayer::ImageNoShift {
-                                        bitmap: (if wireless {
-                                            &self.icon_hs_connect
-                                        } else {
-                                            &self.icon_hs_disconnect
-                                        })
-                                        .clone(),
-                                        x: 8,
-                                        y: 8,
-                                    }),
-                                );
-                                self.notif_expiry = Local::now() + TimeDelta::from_std(NOTIF_DUR).unwrap();
-                                force_redraw = true;
-                            }
-                        }
+                    ggoled_lib::DeviceEvent::HeadsetConnection { wireless, .. }
+                        if Some(wireless) != self.is_connected =>
+                    {
+                        self.is_connected = Some(wireless);
+                        self.notify(Notification::Connection { wireless });
+                        force_redraw = true;
+                    }
+                    ggoled_lib::DeviceEvent::Battery { headset, charging } => {
+                        self.battery = Some((headset.min(BATTERY_LEVEL_MAX), charging != 0));
                     }
                     _ => {}
                 },
             }
         }
 
+        if self.poll_album_art() {
+            force_redraw = true;
+        }
+
         let time = Local::now();
-        let time_changed = time.second() != self.last_time.second();
+        // Blinking the colon needs a redraw every half second instead of every second
+        let colon_on = |time: &DateTime<Local>| time.timestamp_subsec_millis() < 500;
+        let time_changed = time.second() != self.last_time.second()
+            || (self.config.blink_colon && colon_on(&time) != colon_on(&self.last_time));
 
         if !time_changed && !force_redraw {
             return;
         }
         self.last_time = time;
 
-        if !self.notif_layers.is_empty() && time >= self.notif_expiry {
+        self.apply_brightness_schedule(&time);
+
+        if !self.notif_layers.is_empty() && self.notif_expiry.is_some_and(|expiry| time >= expiry) {
             self.clear_notification();
         }
 
+        // Nothing is drawn while the display is off, so layers are only added again once it's turned back on
+        if self.display_off {
+            return;
+        }
+
         let idle_seconds = get_idle_seconds();
-        if self.config.idle_timeout && idle_seconds >= IDLE_TIMEOUT_SECS {
-            self.dev.clear_layers();
-            self.last_media = None;
+        if self.config.idle_timeout && idle_seconds >= self.config.idle_timeout_secs.max(MIN_IDLE_TIMEOUT_SECS) {
+            self.clear_all_layers();
             return;
         }
 
@@ -789,13 +2006,63 @@ lon = {}"#,
             None
         };
 
-        let time_y = if media.is_some() { Some(8) } else { None };
+        if self.slideshow_layer.is_none() && !self.slideshow_images.is_empty() {
+            self.slideshow_layer = Some(self.dev.add_layer(DrawLayer::Slideshow {
+                images: self.slideshow_images.clone(),
+                interval: Duration::from_secs(self.config.slideshow_interval_secs),
+                x: 0,
+                y: 0,
+            }));
+        }
+
+        self.update_battery();
+        self.update_custom_text();
+        #[cfg(feature = "stats")]
+        self.update_stats(time);
+
+        // Keep time and media clear of the stats overlay at the bottom of the screen
+        let line_h = |texter: &Option<TextRenderer>| {
+            texter
+                .as_ref()
+                .map_or(self.dev.font_line_height(), TextRenderer::line_height) as isize
+        };
+        let time_line_h = line_h(&self.time_texter);
+        let media_line_h = line_h(&self.media_texter);
+        let stats_h = self.stats_height();
+        let top_y = if stats_h > 0 {
+            8.min(self.dev.height() as isize - stats_h - time_line_h - 2 * media_line_h)
+                .max(0)
+        } else {
+            8
+        };
+        let layout = self.config.layout;
+        let clock_position = self.config.clock_position;
+        let time_y = if layout.time.y.is_some() {
+            layout.time.y
+        } else if clock_position.is_bottom() {
+            Some((self.dev.height() as isize - stats_h - time_line_h).max(0))
+        } else if clock_position != ConfigClockPosition::Auto {
+            Some(0)
+        } else if media.is_some() {
+            Some(top_y)
+        } else if stats_h > 0 {
+            Some(((self.dev.height() as isize - stats_h - time_line_h) / 2).max(0))
+        } else {
+            None
+        };
 
         // Build the full display string with time and weather
         let display_str = if self.config.show_time {
-            let time_formatted = time.format("%I:%M %p").to_string();
-            if self.config.show_weather && self.weather.temperature.is_some() {
-                let temp = self.weather.temperature.unwrap();
+            let mut time_formatted = if self.config.clock_show_seconds {
+                time.format(&time_format_with_seconds(&self.config.time_format))
+                    .to_string()
+            } else {
+                time.format(&self.config.time_format).to_string()
+            };
+            if self.config.blink_colon && !colon_on(&time) {
+                time_formatted = time_formatted.replace(':', " ");
+            }
+            if let Some(temp) = self.weather.temperature.filter(|_| self.config.show_weather) {
                 let unit = match self.weather.units {
                     WeatherUnits::Metric => "C",
                     WeatherUnits::Imperial => "F",
@@ -813,11 +2080,16 @@ lon = {}"#,
             String::new()
         };
 
-        let media_changed = media != self.last_media;
+        // The playback position changes all the time, so only a different track redraws the text
+        let media_changed = match (&media, &self.last_media) {
+            (Some(media), Some(last_media)) => !media.same_track(last_media),
+            (media, last_media) => media.is_some() != last_media.is_some(),
+        };
         let media_text = media
             .as_ref()
-            .map(|m| format!("{}\n{}", m.title, m.artist))
+            .map(|m| format_media(&self.config.media_format, m))
             .filter(|_| media_changed);
+        let album_art = media.as_ref().filter(|_| media_changed).and_then(|m| self.album_art(m));
         let old_time_layers = std::mem::take(&mut self.time_layers);
         let old_media_layers = if media_changed {
             std::mem::take(&mut self.media_layers)
@@ -826,20 +2098,65 @@ lon = {}"#,
         };
         let mut new_time_layers = vec![];
         let mut new_media_layers = vec![];
-        let media_y = 8 + self.dev.font_line_height() as isize;
+        let time_x = layout.time.x.or_else(|| match clock_position {
+            ConfigClockPosition::TopLeft | ConfigClockPosition::BottomLeft => Some(0),
+            ConfigClockPosition::TopRight | ConfigClockPosition::BottomRight => {
+                let width = match &self.time_texter {
+                    Some(font) => font.measure_line_widths(&display_str).first().copied().unwrap_or(0),
+                    None => self.dev.measure_line_widths(&display_str).first().copied().unwrap_or(0),
+                };
+                Some((self.dev.width() as isize - width as isize).max(0))
+            }
+            _ => None,
+        });
+        // Media goes below a clock at the top, or takes the top itself when the clock is at the bottom
+        let media_y = layout.media.y.unwrap_or(match clock_position {
+            ConfigClockPosition::Auto => top_y + time_line_h,
+            position if position.is_bottom() => top_y,
+            _ => time_line_h,
+        });
+        let media_x = layout
+            .media
+            .x
+            .or(album_art.as_ref().map(|art| art.w as isize + ALBUM_ART_MARGIN));
+        let time_texter = &self.time_texter;
+        let media_texter = &self.media_texter;
+        let overflow = self.config.text_overflow.to_api();
+        let transition = self.config.transition.map(ConfigTransition::to_api);
 
         self.dev.transact_layers(|txn| {
-            txn.remove_layers(&old_time_layers);
-            // Use merged font (cozette + siji) for everything
+            // Elements without their own font use the default one (merged cozette + siji)
+            let mut time_layers = vec![];
             if !display_str.is_empty() {
-                new_time_layers = txn.add_text_with_mode(&display_str, None, time_y, true, TextOverflowMode::Scroll);
+                time_layers = match time_texter {
+                    Some(font) => txn.add_text_with_font(&display_str, font, time_x, time_y, true, overflow),
+                    None => txn.add_text_with_mode(&display_str, time_x, time_y, true, overflow),
+                };
+            }
+            // With a transition, lines that are still there animate to their new text instead of being replaced
+            for id in &time_layers {
+                txn.set_layer_transition(*id, transition);
             }
+            new_time_layers = txn.morph_layers(&old_time_layers, time_layers);
             if media_changed {
-                txn.remove_layers(&old_media_layers);
+                let mut media_layers = vec![];
                 if let Some(media_text) = &media_text {
-                    new_media_layers =
-                        txn.add_text_with_mode(media_text, None, Some(media_y), true, TextOverflowMode::Scroll);
+                    media_layers = match media_texter {
+                        Some(font) => txn.add_text_with_font(media_text, font, media_x, Some(media_y), true, overflow),
+                        None => txn.add_text_with_mode(media_text, media_x, Some(media_y), true, overflow),
+                    };
+                }
+                if let Some(bitmap) = album_art {
+                    media_layers.push(txn.add_layer(DrawLayer::ImageNoShift {
+                        bitmap,
+                        x: 0,
+                        y: media_y,
+                    }));
+                }
+                for id in &media_layers {
+                    txn.set_layer_transition(*id, transition);
                 }
+                new_media_layers = txn.morph_layers(&old_media_layers, media_layers);
             }
         });
         self.time_layers = new_time_layers;
@@ -849,6 +2166,63 @@ lon = {}"#,
         }
     }
 
+    fn album_art(&mut self, media: &Media) -> Option<Arc<ggoled_lib::Bitmap>> {
+        if !self.config.show_album_art {
+            return None;
+        }
+        if let Some(art) = &media.art {
+            return Some(art.clone());
+        }
+        let url = media.art_url.as_ref()?;
+        match &self.album_art {
+            Some((cached_url, AlbumArt::Loaded(art))) if cached_url == url => art.clone(),
+            Some((cached_url, AlbumArt::Loading(_))) if cached_url == url => None,
+            _ => {
+                let (tx, rx) = channel();
+                let thread_url = url.clone();
+                let spawned = std::thread::Builder::new()
+                    .name("ggoled-album-art".to_string())
+                    .spawn(move || {
+                        let art = load_album_art(&thread_url, ALBUM_ART_SIZE)
+                            .inspect_err(|err| debug!(?err, url = thread_url, "failed to load album art"))
+                            .ok()
+                            .map(Arc::new);
+                        _ = tx.send(art);
+                    });
+                let art = match spawned {
+                    Ok(_) => AlbumArt::Loading(rx),
+                    Err(err) => {
+                        warn!(?err, "failed to spawn album art thread");
+                        AlbumArt::Loaded(None)
+                    }
+                };
+                self.album_art = Some((url.clone(), art));
+                None
+            }
+        }
+    }
+
+    // Picks up album art that finished loading. Returns whether the media needs to be drawn again to show it.
+    fn poll_album_art(&mut self) -> bool {
+        let Some((_, AlbumArt::Loading(rx))) = &self.album_art else {
+            return false;
+        };
+        let art = match rx.try_recv() {
+            Ok(art) => art,
+            Err(TryRecvError::Empty) => return false,
+            Err(TryRecvError::Disconnected) => None,
+        };
+        let loaded = art.is_some();
+        if let Some((_, state)) = &mut self.album_art {
+            *state = AlbumArt::Loaded(art);
+        }
+        if loaded {
+            // Makes the media count as changed, so its layers are added again with the art
+            self.last_media = None;
+        }
+        loaded
+    }
+
     fn shutdown(self) {
         let dev = self.dev.stop();
         _ = dev.return_to_ui();
@@ -874,10 +2248,21 @@ fn create_tray(config: &Config, capabilities: PlatformCapabilities) -> anyhow::R
     let tm_media_check = CheckMenuItem::new("Show playing media", true, config.show_media, None);
     let tm_media_paused_check = CheckMenuItem::new("Show paused media", true, config.show_media_paused, None);
     let tm_notif_check = CheckMenuItem::new("Show connection notifications", true, config.show_notifications, None);
+    let tm_battery_check = CheckMenuItem::new("Show headset battery", true, config.show_battery, None);
+    let tm_custom_text_check = CheckMenuItem::new(
+        "Show custom text",
+        config.custom_text.is_some(),
+        config.show_custom_text,
+        None,
+    );
     let tm_idle_check = CheckMenuItem::new("Screensaver when idle", true, config.idle_timeout, None);
+    let tm_display_off_check = CheckMenuItem::new("Display off", true, false, None);
     let tm_autostart_check = CheckMenuItem::new("Start at login", true, config.autostart, None);
     let tm_weather_check = CheckMenuItem::new("Show weather", true, config.show_weather, None);
+    #[cfg(feature = "stats")]
+    let tm_stats_check = CheckMenuItem::new("Show system stats", true, config.show_stats, None);
     let tm_open_config = MenuItem::new("Open config file", true, None);
+    let tm_dismiss_notif = MenuItem::new("Dismiss notification", true, None);
     let tm_volume_down = MenuItem::new("Volume down", true, None);
     let tm_volume_up = MenuItem::new("Volume up", true, None);
     let tm_volume_mute = MenuItem::new("Mute", true, None);
@@ -885,10 +2270,13 @@ fn create_tray(config: &Config, capabilities: PlatformCapabilities) -> anyhow::R
     let tm_volume_50 = MenuItem::new("50%", true, None);
     let tm_volume_75 = MenuItem::new("75%", true, None);
     let tm_volume_100 = MenuItem::new("100%", true, None);
-    #[cfg(target_os = "macos")]
+    let tm_play_pause = MenuItem::new("Play/pause", capabilities.media_control, None);
+    let tm_next_track = MenuItem::new("Next track", capabilities.media_control, None);
+    let tm_previous_track = MenuItem::new("Previous track", capabilities.media_control, None);
+    #[cfg(any(target_os = "macos", target_os = "windows"))]
     let tm_pass_through_volume_keys_check = CheckMenuItem::new(
         "Pass through media volume keys",
-        true,
+        !matches!(config.volume_key_action, ConfigVolumeKeyAction::SystemVolume),
         config.pass_through_volume_keys,
         None,
     );
@@ -897,8 +2285,28 @@ fn create_tray(config: &Config, capabilities: PlatformCapabilities) -> anyhow::R
     menu.append(&tm_media_check)?;
     menu.append(&tm_media_paused_check)?;
     menu.append(&tm_weather_check)?;
+    menu.append(&tm_custom_text_check)?;
+    #[cfg(feature = "stats")]
+    menu.append(&tm_stats_check)?;
     menu.append(&tm_notif_check)?;
+    menu.append(&tm_dismiss_notif)?;
+    menu.append(&tm_battery_check)?;
     menu.append(&tm_idle_check)?;
+    let tm_idle_submenu = Submenu::new("Screensaver delay", capabilities.idle_timeout);
+    let tm_idle_presets = IDLE_TIMEOUT_PRESETS
+        .iter()
+        .map(|(secs, label)| {
+            (
+                *secs,
+                CheckMenuItem::new(*label, true, config.idle_timeout_secs == *secs, None),
+            )
+        })
+        .collect::<Vec<_>>();
+    for (_, item) in &tm_idle_presets {
+        tm_idle_submenu.append(item)?;
+    }
+    menu.append(&tm_idle_submenu)?;
+    menu.append(&tm_display_off_check)?;
     menu.append(&tm_autostart_check)?;
     menu.append(&tm_open_config)?;
 
@@ -911,21 +2319,57 @@ fn create_tray(config: &Config, capabilities: PlatformCapabilities) -> anyhow::R
     tm_volume_submenu.append(&tm_volume_75)?;
     tm_volume_submenu.append(&tm_volume_100)?;
     menu.append(&tm_volume_submenu)?;
-    #[cfg(target_os = "macos")]
+
+    let tm_playback_submenu = Submenu::new("Media playback", capabilities.media_control);
+    tm_playback_submenu.append(&tm_play_pause)?;
+    tm_playback_submenu.append(&tm_next_track)?;
+    tm_playback_submenu.append(&tm_previous_track)?;
+    menu.append(&tm_playback_submenu)?;
+    #[cfg(any(target_os = "macos", target_os = "windows"))]
     menu.append(&tm_pass_through_volume_keys_check)?;
 
     let tm_shift_submenu = Submenu::new("OLED screen shift", true);
-    let tm_shift_off = CheckMenuItem::new("Off", true, matches!(config.oled_shift, ConfigShiftMode::Off), None);
-    let tm_shift_simple = CheckMenuItem::new(
-        "Simple",
-        true,
-        matches!(config.oled_shift, ConfigShiftMode::Simple),
-        None,
-    );
-    tm_shift_submenu.append(&tm_shift_off)?;
-    tm_shift_submenu.append(&tm_shift_simple)?;
+    let tm_shift_modes = SHIFT_MODES
+        .iter()
+        .map(|(mode, label)| {
+            (
+                *mode,
+                CheckMenuItem::new(*label, true, config.oled_shift == *mode, None),
+            )
+        })
+        .collect::<Vec<_>>();
+    for (_, item) in &tm_shift_modes {
+        tm_shift_submenu.append(item)?;
+    }
     menu.append(&tm_shift_submenu)?;
 
+    let tm_brightness_submenu = Submenu::new("Brightness", true);
+    let tm_brightness_presets = BRIGHTNESS_PRESETS
+        .iter()
+        .map(|level| {
+            let item = CheckMenuItem::new(level.to_string(), true, config.brightness == Some(*level), None);
+            (*level, item)
+        })
+        .collect::<Vec<_>>();
+    for (_, item) in &tm_brightness_presets {
+        tm_brightness_submenu.append(item)?;
+    }
+    menu.append(&tm_brightness_submenu)?;
+
+    let tm_profile_submenu = Submenu::new("Profile", true);
+    let tm_profiles = std::iter::once(None)
+        .chain(Config::profiles().into_iter().map(Some))
+        .map(|profile| {
+            let label = profile.as_deref().unwrap_or("Default");
+            let item = CheckMenuItem::new(label, true, profile == config.profile, None);
+            (profile, item)
+        })
+        .collect::<Vec<_>>();
+    for (_, item) in &tm_profiles {
+        tm_profile_submenu.append(item)?;
+    }
+    menu.append(&tm_profile_submenu)?;
+
     let tm_quit = MenuItem::new("Quit", true, None);
     menu.append(&tm_quit)?;
 
@@ -958,10 +2402,17 @@ fn create_tray(config: &Config, capabilities: PlatformCapabilities) -> anyhow::R
         tm_media_check,
         tm_media_paused_check,
         tm_notif_check,
+        tm_battery_check,
+        tm_custom_text_check,
         tm_idle_check,
+        tm_idle_presets,
+        tm_display_off_check,
         tm_autostart_check,
         tm_weather_check,
+        #[cfg(feature = "stats")]
+        tm_stats_check,
         tm_open_config,
+        tm_dismiss_notif,
         tm_volume_down,
         tm_volume_up,
         tm_volume_mute,
@@ -969,10 +2420,14 @@ fn create_tray(config: &Config, capabilities: PlatformCapabilities) -> anyhow::R
         tm_volume_50,
         tm_volume_75,
         tm_volume_100,
-        #[cfg(target_os = "macos")]
+        tm_play_pause,
+        tm_next_track,
+        tm_previous_track,
+        #[cfg(any(target_os = "macos", target_os = "windows"))]
         tm_pass_through_volume_keys_check,
-        tm_shift_off,
-        tm_shift_simple,
+        tm_shift_modes,
+        tm_brightness_presets,
+        tm_profiles,
         tm_quit,
     })
 }
@@ -982,16 +2437,7 @@ fn main() {
     info!("ggoled_app starting");
     let mut config = Config::load();
     let capabilities = capabilities();
-
-    config.show_media = config.show_media && capabilities.media;
-    config.show_media_paused = config.show_media_paused && capabilities.media;
-    config.idle_timeout = config.idle_timeout && capabilities.idle_timeout;
-    config.autostart = if capabilities.autostart { get_autostart() } else { false };
-    config.show_weather = config.show_weather && config.weather_coords.is_some();
-    #[cfg(not(target_os = "macos"))]
-    {
-        config.pass_through_volume_keys = false;
-    }
+    config.restrict_to(&capabilities);
 
     #[cfg(target_os = "macos")]
     let mut event_loop = EventLoopBuilder::<UserEvent>::with_user_event().build();
@@ -1017,6 +2463,9 @@ fn main() {
 
     let mut runtime: Option<RuntimeState> = None;
     let mut initial_config = Some(config);
+    // Config and tray waiting for a device to start with. `connect_retry_at` is only set once the first attempt failed.
+    let mut pending: Option<(Config, TrayState)> = None;
+    let mut connect_retry_at: Option<Instant> = None;
 
     event_loop.run(move |event, _, control_flow| {
         let tick_dur = runtime.as_ref().map_or(TICK_DUR_NORMAL, RuntimeState::tick_duration);
@@ -1036,16 +2485,12 @@ fn main() {
                         return;
                     }
                 };
-                match RuntimeState::new(config, capabilities, tray) {
-                    Ok(state) => runtime = Some(state),
-                    Err(err) => {
-                        show_error_dialog(&format!("Error: {err:?}"));
-                        *control_flow = ControlFlow::Exit;
-                    }
-                }
+                pending = Some((config, tray));
             }
             Event::UserEvent(UserEvent::MenuEvent(event)) => {
-                if let Some(state) = runtime.as_mut() {
+                if pending.as_ref().is_some_and(|(_, tray)| event.id == tray.tm_quit.id()) {
+                    *control_flow = ControlFlow::Exit;
+                } else if let Some(state) = runtime.as_mut() {
                     let should_quit = state.handle_menu_event(event);
                     if should_quit {
                         if let Some(state) = runtime.take() {
@@ -1064,6 +2509,38 @@ fn main() {
             Event::MainEventsCleared => {
                 if let Some(state) = runtime.as_mut() {
                     state.tick();
+                } else if connect_retry_at.is_none_or(|at| Instant::now() >= at) {
+                    let Some((config, tray)) = pending.take() else {
+                        return;
+                    };
+                    match Device::connect() {
+                        Ok(device) => {
+                            if connect_retry_at.is_some() {
+                                info!("device connected");
+                                _ = tray.tray.set_icon(Some(tray.icon_ok.clone()));
+                            }
+                            match RuntimeState::new(config, capabilities, tray, device) {
+                                Ok(state) => runtime = Some(state),
+                                Err(err) => {
+                                    show_error_dialog(&format!("Error: {err:?}"));
+                                    *control_flow = ControlFlow::Exit;
+                                }
+                            }
+                        }
+                        // Autostart can race the device, so keep the tray around and try again
+                        Err(err) if config.wait_for_device => {
+                            if connect_retry_at.is_none() {
+                                info!(%err, "waiting for a device to be connected");
+                                _ = tray.tray.set_icon(Some(tray.icon_error.clone()));
+                            }
+                            connect_retry_at = Some(Instant::now() + DEVICE_RETRY_PERIOD);
+                            pending = Some((config, tray));
+                        }
+                        Err(err) => {
+                            show_error_dialog(&format!("Error: {err:?}"));
+                            *control_flow = ControlFlow::Exit;
+                        }
+                    }
                 }
             }
             Event::LoopDestroyed => {
@@ -0,0 +1,102 @@
+use super::{Media, PlatformCapabilities};
+use draconis::{init_static_plugins, initialize_plugin_manager, shutdown_plugin_manager, CacheManager, Plugin};
+
+pub struct MediaControl {
+    plugin: Option<Plugin>,
+    cache: CacheManager,
+}
+
+impl MediaControl {
+    pub fn new() -> MediaControl {
+        initialize_plugin_manager();
+        let count = init_static_plugins();
+        if count == 0 {
+            tracing::warn!("No static plugins registered");
+        }
+
+        let mut cache = CacheManager::new();
+        let mut plugin = Plugin::new("NowPlayingPlugin").ok();
+
+        if let Some(ref mut p) = plugin {
+            if let Err(e) = p.initialize(&mut cache) {
+                tracing::warn!("Failed to initialize NowPlayingPlugin: {:?}", e);
+                plugin = None;
+            }
+        }
+
+        if plugin.is_none() {
+            tracing::warn!("Failed to load NowPlayingPlugin");
+        }
+
+        MediaControl { plugin, cache }
+    }
+
+    pub fn get_media(&mut self, _include_paused: bool) -> Option<Media> {
+        let plugin = self.plugin.as_mut()?;
+
+        if let Err(e) = plugin.collect_data(&mut self.cache) {
+            let last_error = plugin.get_last_error();
+            tracing::warn!("Failed to collect plugin data: {:?} (last_error: {:?})", e, last_error);
+            return None;
+        }
+
+        let fields = plugin.get_fields().ok()?;
+
+        tracing::debug!("Plugin fields: {:?}", fields);
+
+        let title = fields.get("title")?.clone();
+        let artist = fields.get("artist").cloned().unwrap_or_default();
+        let album = fields.get("album").cloned().unwrap_or_default();
+
+        if title.is_empty() {
+            return None;
+        }
+
+        Some(Media {
+            title,
+            artist,
+            album,
+            position: None,
+            length: None,
+            art_url: None,
+            art: None,
+        })
+    }
+
+    pub fn play_pause(&self) -> anyhow::Result<()> {
+        Self::unsupported()
+    }
+    pub fn next(&self) -> anyhow::Result<()> {
+        Self::unsupported()
+    }
+    pub fn previous(&self) -> anyhow::Result<()> {
+        Self::unsupported()
+    }
+    // The NowPlaying plugin only reports what's playing
+    fn unsupported() -> anyhow::Result<()> {
+        anyhow::bail!("the NowPlaying plugin can't control playback")
+    }
+}
+
+impl Drop for MediaControl {
+    fn drop(&mut self) {
+        shutdown_plugin_manager();
+    }
+}
+
+pub fn capabilities() -> PlatformCapabilities {
+    PlatformCapabilities {
+        media: true,
+        media_control: false,
+        idle_timeout: false,
+        autostart: false,
+    }
+}
+
+pub fn set_autostart(_enabled: bool) {}
+pub fn get_autostart() -> bool {
+    false
+}
+pub fn get_idle_seconds() -> usize {
+    0
+}
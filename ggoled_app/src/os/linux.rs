@@ -1,5 +1,6 @@
 use super::{Media, PlatformCapabilities};
-use mpris::{PlaybackStatus, PlayerFinder};
+use anyhow::Context;
+use mpris::{PlaybackStatus, Player, PlayerFinder};
 use std::sync::atomic::{AtomicBool, Ordering};
 use tracing::warn;
 
@@ -16,6 +17,7 @@ pub fn capabilities() -> PlatformCapabilities {
     }
     PlatformCapabilities {
         media: true,
+        media_control: true,
         idle_timeout,
         autostart: false,
     }
@@ -51,8 +53,27 @@ impl MediaControl {
         Some(Media {
             title: title.to_string(),
             artist: artist.to_string(),
+            album: meta.album_name().unwrap_or_default().to_string(),
+            position: player.get_position().ok(),
+            length: meta.length(),
+            art_url: meta.art_url().map(str::to_string),
+            art: None,
         })
     }
+
+    fn active_player(&self) -> anyhow::Result<Player> {
+        let pf = self.pf.as_ref().context("no MPRIS connection")?;
+        Ok(pf.find_active()?)
+    }
+    pub fn play_pause(&self) -> anyhow::Result<()> {
+        Ok(self.active_player()?.play_pause()?)
+    }
+    pub fn next(&self) -> anyhow::Result<()> {
+        Ok(self.active_player()?.next()?)
+    }
+    pub fn previous(&self) -> anyhow::Result<()> {
+        Ok(self.active_player()?.previous()?)
+    }
 }
 
 pub fn get_idle_seconds() -> usize {
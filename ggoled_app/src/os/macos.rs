@@ -1,13 +1,14 @@
-use super::macos_mediaremote::NowPlaying;
-use super::{Media, PlatformCapabilities, VolumeKeySignal};
+use super::macos_mediaremote::{send_command, MediaRemoteCommand, NowPlaying};
+use super::{Media, PlatformCapabilities, VolumeKeyMap, VolumeKeySignal};
 use auto_launch::{AutoLaunch, MacOSLaunchMode};
+use block2::RcBlock;
 use core_foundation::array::CFArray;
-use core_foundation::base::TCFType;
+use core_foundation::base::{CFRelease, TCFType};
 use core_foundation::boolean::CFBoolean;
 use core_foundation::dictionary::{CFDictionary, CFDictionaryRef};
 use core_foundation::mach_port::{CFMachPort, CFMachPortRef};
 use core_foundation::number::CFNumber;
-use core_foundation::runloop::{kCFRunLoopCommonModes, CFRunLoop};
+use core_foundation::runloop::{kCFRunLoopCommonModes, kCFRunLoopDefaultMode, CFRunLoop};
 use core_foundation::string::{CFString, CFStringRef};
 use core_graphics::event::{
     CGEventTap, CGEventTapLocation, CGEventTapOptions, CGEventTapPlacement, CGEventType, EventField,
@@ -17,8 +18,9 @@ use dispatch2::ffi::{dispatch_queue_create, dispatch_queue_t, DISPATCH_QUEUE_SER
 use std::ffi::c_void;
 use std::panic::{catch_unwind, AssertUnwindSafe};
 use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
-use std::sync::mpsc::{channel, Receiver, Sender};
-use std::sync::{Arc, Mutex};
+use std::sync::mpsc::{channel, Receiver, Sender, TryIter};
+use std::sync::{Arc, Mutex, PoisonError};
+use std::time::Duration;
 use tracing::{debug, error, warn};
 
 const APP_NAME: &str = "ggoled_app";
@@ -61,9 +63,12 @@ unsafe extern "C" {
     fn IOHIDRequestAccess(request_type: i32) -> bool;
     fn IOHIDManagerCreate(allocator: *const c_void, options: u32) -> IOHIDManagerRef;
     fn IOHIDManagerOpen(manager: IOHIDManagerRef, options: u32) -> IOReturn;
+    fn IOHIDManagerClose(manager: IOHIDManagerRef, options: u32) -> IOReturn;
     fn IOHIDManagerSetDeviceMatchingMultiple(manager: IOHIDManagerRef, multiple: *const c_void);
     fn IOHIDManagerSetDispatchQueue(manager: IOHIDManagerRef, queue: dispatch_queue_t);
     fn IOHIDManagerActivate(manager: IOHIDManagerRef);
+    fn IOHIDManagerCancel(manager: IOHIDManagerRef);
+    fn IOHIDManagerSetCancelHandler(manager: IOHIDManagerRef, handler: &block2::DynBlock<dyn Fn()>);
     fn IOHIDManagerRegisterInputValueCallback(
         manager: IOHIDManagerRef,
         callback: Option<IOHIDValueCallback>,
@@ -96,6 +101,7 @@ fn volume_key_debug(msg: impl AsRef<str>) {
 pub fn capabilities() -> PlatformCapabilities {
     PlatformCapabilities {
         media: true,
+        media_control: true,
         idle_timeout: true,
         autostart: true,
     }
@@ -172,8 +178,32 @@ impl MediaControl {
         Some(Media {
             title: title.to_string(),
             artist: artist.to_string(),
+            album: String::new(),
+            position: None,
+            length: None,
+            art_url: None,
+            art: None,
         })
     }
+
+    fn send(&self, command: MediaRemoteCommand) -> anyhow::Result<()> {
+        if self.now_playing.is_none() {
+            anyhow::bail!("MediaRemote is unavailable");
+        }
+        if !send_command(command) {
+            anyhow::bail!("MediaRemote didn't accept {command:?}");
+        }
+        Ok(())
+    }
+    pub fn play_pause(&self) -> anyhow::Result<()> {
+        self.send(MediaRemoteCommand::TogglePlayPause)
+    }
+    pub fn next(&self) -> anyhow::Result<()> {
+        self.send(MediaRemoteCommand::NextTrack)
+    }
+    pub fn previous(&self) -> anyhow::Result<()> {
+        self.send(MediaRemoteCommand::PreviousTrack)
+    }
 }
 
 pub fn get_idle_seconds() -> usize {
@@ -258,22 +288,38 @@ pub fn ensure_accessibility_permission(prompt: bool) -> bool {
     trusted
 }
 
-pub fn start_volume_key_listener() -> Option<Receiver<VolumeKeySignal>> {
+/// Running volume key listener. Dropping it stops the listener threads and removes their event taps, so the
+/// volume keys go back to controlling macOS.
+pub struct VolumeKeyListener {
+    rx: Receiver<VolumeKeySignal>,
+    stopped: Arc<AtomicBool>,
+}
+impl VolumeKeyListener {
+    /// Volume keys pressed since the last call.
+    pub fn signals(&self) -> TryIter<'_, VolumeKeySignal> {
+        self.rx.try_iter()
+    }
+}
+impl Drop for VolumeKeyListener {
+    fn drop(&mut self) {
+        volume_key_debug("stopping volume key listener threads");
+        self.stopped.store(true, Ordering::Relaxed);
+    }
+}
+
+pub fn start_volume_key_listener(key_map: VolumeKeyMap) -> Option<VolumeKeyListener> {
     const MEDIA_KEYCODE_VOLUME_UP: i64 = 72;
     const MEDIA_KEYCODE_VOLUME_DOWN: i64 = 73;
     const MEDIA_KEYCODE_VOLUME_MUTE: i64 = 74;
-    const FKEYCODE_F10: i64 = 109;
-    const FKEYCODE_F11: i64 = 103;
-    const FKEYCODE_F12: i64 = 111;
     const K_IO_HID_REQUEST_TYPE_LISTEN_EVENT: i32 = 1;
     const K_IO_HID_PAGE_CONSUMER: u32 = 0x0C;
     const K_IO_HID_USAGE_CONSUMER_CONTROL: u32 = 0x01;
-    const K_IO_HID_USAGE_CONSUMER_MUTE: u32 = 0xE2;
-    const K_IO_HID_USAGE_CONSUMER_VOLUME_INCREMENT: u32 = 0xE9;
-    const K_IO_HID_USAGE_CONSUMER_VOLUME_DECREMENT: u32 = 0xEA;
     const SYSTEM_DEFINED_EVENT_TYPE: u32 = 14;
     const SUPPRESS_WINDOW_MS: u64 = 150;
     const HID_DUPLICATE_WINDOW_MS: u64 = 12;
+    const CROSS_SOURCE_DUPLICATE_WINDOW_MS: u64 = 50;
+    // How long a listener thread may take to notice that it was stopped
+    const STOP_POLL_INTERVAL: Duration = Duration::from_millis(250);
 
     fn now_unix_millis() -> u64 {
         std::time::SystemTime::now()
@@ -282,8 +328,31 @@ pub fn start_volume_key_listener() -> Option<Receiver<VolumeKeySignal>> {
             .unwrap_or(0)
     }
 
+    // Runs the current thread's run loop, and with it the event taps added to it, until the listener is stopped
+    fn run_until_stopped(stopped: &AtomicBool) {
+        while !stopped.load(Ordering::Relaxed) {
+            CFRunLoop::run_in_mode(unsafe { kCFRunLoopDefaultMode }, STOP_POLL_INTERVAL, false);
+        }
+    }
+
     struct SuppressState {
         suppress_until_ms: AtomicU64,
+        last_signal: Mutex<Option<(VolumeKeySignal, u64)>>,
+    }
+    impl SuppressState {
+        // The HID listener and the keydown tap can both see the same key press, so only the first to report it
+        // within the window sends a signal
+        fn claim_signal(&self, signal: VolumeKeySignal, now_ms: u64) -> bool {
+            // This runs inside event tap and HID callbacks, where a panic can't unwind
+            let mut last_signal = self.last_signal.lock().unwrap_or_else(PoisonError::into_inner);
+            let duplicate = last_signal.is_some_and(|(last, at_ms)| {
+                last == signal && now_ms.saturating_sub(at_ms) <= CROSS_SOURCE_DUPLICATE_WINDOW_MS
+            });
+            if !duplicate {
+                *last_signal = Some((signal, now_ms));
+            }
+            !duplicate
+        }
     }
 
     struct HidDedupeState {
@@ -294,6 +363,7 @@ pub fn start_volume_key_listener() -> Option<Receiver<VolumeKeySignal>> {
 
     struct HidListenerContext {
         tx: Sender<VolumeKeySignal>,
+        key_map: VolumeKeyMap,
         suppress_state: Arc<SuppressState>,
         dedupe: Arc<Mutex<HidDedupeState>>,
     }
@@ -316,12 +386,7 @@ pub fn start_volume_key_listener() -> Option<Receiver<VolumeKeySignal>> {
         let usage = unsafe { IOHIDElementGetUsage(element) };
         let raw_value = unsafe { IOHIDValueGetIntegerValue(value) };
         let signal = if usage_page == K_IO_HID_PAGE_CONSUMER && raw_value != 0 {
-            match usage {
-                K_IO_HID_USAGE_CONSUMER_VOLUME_INCREMENT => Some(VolumeKeySignal::Up),
-                K_IO_HID_USAGE_CONSUMER_VOLUME_DECREMENT => Some(VolumeKeySignal::Down),
-                K_IO_HID_USAGE_CONSUMER_MUTE => Some(VolumeKeySignal::Mute),
-                _ => None,
-            }
+            ctx.key_map.hid_usage_signal(usage)
         } else {
             None
         };
@@ -332,7 +397,7 @@ pub fn start_volume_key_listener() -> Option<Receiver<VolumeKeySignal>> {
         if let Some(signal) = signal {
             let now_ms = now_unix_millis();
             let duplicate = {
-                let mut dedupe = ctx.dedupe.lock().expect("hid dedupe mutex poisoned");
+                let mut dedupe = ctx.dedupe.lock().unwrap_or_else(PoisonError::into_inner);
                 let is_dup = dedupe.usage == usage
                     && dedupe.value == raw_value
                     && now_ms.saturating_sub(dedupe.at_ms) <= HID_DUPLICATE_WINDOW_MS;
@@ -358,6 +423,10 @@ pub fn start_volume_key_listener() -> Option<Receiver<VolumeKeySignal>> {
                 "hid mapped {:?}; suppress_until_ms={}",
                 signal, suppress_until_ms
             ));
+            if !ctx.suppress_state.claim_signal(signal, now_ms) {
+                volume_key_debug(format!("hid {signal:?} already sent by the keydown tap"));
+                return;
+            }
             if ctx.tx.send(signal).is_err() {
                 volume_key_debug("receiver dropped; ignoring HID consumer key");
             }
@@ -405,7 +474,7 @@ pub fn start_volume_key_listener() -> Option<Receiver<VolumeKeySignal>> {
         event
     }
 
-    fn start_system_defined_suppressor_listener(suppress_state: Arc<SuppressState>) -> bool {
+    fn start_system_defined_suppressor_listener(suppress_state: Arc<SuppressState>, stopped: Arc<AtomicBool>) -> bool {
         let res = std::thread::Builder::new()
             .name("ggoled-volume-keys-suppressor".to_string())
             .spawn(move || {
@@ -446,9 +515,11 @@ pub fn start_volume_key_listener() -> Option<Receiver<VolumeKeySignal>> {
                     CGEventTapEnable(tap.as_concrete_TypeRef(), true);
                 }
                 volume_key_debug("system-defined suppressor listener runloop active");
-                CFRunLoop::run_current();
+                run_until_stopped(&stopped);
                 volume_key_debug("system-defined suppressor listener runloop exited");
                 unsafe {
+                    CGEventTapEnable(tap.as_concrete_TypeRef(), false);
+                    run_loop.remove_source(&loop_source, kCFRunLoopCommonModes);
                     drop(Box::from_raw(state_ptr as *mut Arc<SuppressState>));
                 }
             });
@@ -461,13 +532,20 @@ pub fn start_volume_key_listener() -> Option<Receiver<VolumeKeySignal>> {
         true
     }
 
-    fn start_hid_listener(tx: Sender<VolumeKeySignal>, suppress_state: Arc<SuppressState>) -> bool {
+    fn start_hid_listener(
+        tx: Sender<VolumeKeySignal>,
+        key_map: VolumeKeyMap,
+        suppress_state: Arc<SuppressState>,
+        stopped: Arc<AtomicBool>,
+    ) -> bool {
         let res = std::thread::Builder::new()
             .name("ggoled-volume-keys-hid".to_string())
             .spawn(move || {
                 volume_key_debug("hid volume key listener thread started");
+                let usages: Vec<u32> = key_map.hid_usages.iter().map(|(usage, _)| *usage).collect();
                 let tx_ptr = Box::into_raw(Box::new(HidListenerContext {
                     tx,
+                    key_map,
                     suppress_state,
                     dedupe: Arc::new(Mutex::new(HidDedupeState {
                         usage: 0,
@@ -486,26 +564,15 @@ pub fn start_volume_key_listener() -> Option<Receiver<VolumeKeySignal>> {
                 let usage_page_key = CFString::from_static_string("UsagePage");
                 let usage_key = CFString::from_static_string("Usage");
                 let usage_page = CFNumber::from(K_IO_HID_PAGE_CONSUMER as i64);
-                let match_dicts = [
-                    CFDictionary::from_CFType_pairs(&[
-                        (usage_page_key.clone(), usage_page.clone()),
-                        (
-                            usage_key.clone(),
-                            CFNumber::from(K_IO_HID_USAGE_CONSUMER_VOLUME_INCREMENT as i64),
-                        ),
-                    ]),
-                    CFDictionary::from_CFType_pairs(&[
-                        (usage_page_key.clone(), usage_page.clone()),
-                        (
-                            usage_key.clone(),
-                            CFNumber::from(K_IO_HID_USAGE_CONSUMER_VOLUME_DECREMENT as i64),
-                        ),
-                    ]),
-                    CFDictionary::from_CFType_pairs(&[
-                        (usage_page_key, usage_page),
-                        (usage_key, CFNumber::from(K_IO_HID_USAGE_CONSUMER_MUTE as i64)),
-                    ]),
-                ];
+                let match_dicts: Vec<_> = usages
+                    .iter()
+                    .map(|usage| {
+                        CFDictionary::from_CFType_pairs(&[
+                            (usage_page_key.clone(), usage_page.clone()),
+                            (usage_key.clone(), CFNumber::from(*usage as i64)),
+                        ])
+                    })
+                    .collect();
                 let matches: CFArray<CFDictionary<CFString, CFNumber>> = CFArray::from_CFTypes(&match_dicts);
                 let device_usage_page_key = CFString::from_static_string("DeviceUsagePage");
                 let device_usage_key = CFString::from_static_string("DeviceUsage");
@@ -528,19 +595,37 @@ pub fn start_volume_key_listener() -> Option<Receiver<VolumeKeySignal>> {
                 if open_result != 0 {
                     error!(open_result, "failed to open IOHIDManager for input listening");
                     volume_key_debug(format!("IOHIDManagerOpen failed with code={open_result}"));
-                    unsafe { drop(Box::from_raw(tx_ptr as *mut HidListenerContext)) };
+                    unsafe {
+                        CFRelease(manager as *const c_void);
+                        drop(Box::from_raw(tx_ptr as *mut HidListenerContext));
+                    }
                     return;
                 }
                 let queue = unsafe { dispatch_queue_create(std::ptr::null(), DISPATCH_QUEUE_SERIAL) };
+                // Runs on the queue once cancelling has flushed every callback still queued, so it's the first point
+                // where nothing can use the context or the manager anymore
+                let cancel_handler = RcBlock::new(move || unsafe {
+                    IOHIDManagerClose(manager, 0);
+                    CFRelease(manager as *const c_void);
+                    drop(Box::from_raw(tx_ptr as *mut HidListenerContext));
+                    volume_key_debug("hid volume key listener context freed");
+                });
                 unsafe {
                     IOHIDManagerSetDispatchQueue(manager, queue);
+                    IOHIDManagerSetCancelHandler(manager, &cancel_handler);
                     IOHIDManagerActivate(manager);
                 }
                 volume_key_debug("hid volume key listener dispatch queue active");
 
-                loop {
-                    std::thread::sleep(std::time::Duration::from_secs(3600));
+                while !stopped.load(Ordering::Relaxed) {
+                    std::thread::sleep(STOP_POLL_INTERVAL);
+                }
+                // The cancel handler closes and releases the manager and frees the context
+                unsafe {
+                    IOHIDManagerRegisterInputValueCallback(manager, None, std::ptr::null_mut());
+                    IOHIDManagerCancel(manager);
                 }
+                volume_key_debug("hid volume key listener stopped");
             });
         if let Err(err) = res {
             error!(?err, "failed to start hid volume key listener thread");
@@ -551,7 +636,12 @@ pub fn start_volume_key_listener() -> Option<Receiver<VolumeKeySignal>> {
         true
     }
 
-    fn start_keydown_tap_listener(tx: Sender<VolumeKeySignal>, suppress_state: Arc<SuppressState>) -> bool {
+    fn start_keydown_tap_listener(
+        tx: Sender<VolumeKeySignal>,
+        key_map: VolumeKeyMap,
+        suppress_state: Arc<SuppressState>,
+        stopped: Arc<AtomicBool>,
+    ) -> bool {
         let res = std::thread::Builder::new()
             .name("ggoled-volume-keys".to_string())
             .spawn(move || {
@@ -570,16 +660,7 @@ pub fn start_volume_key_listener() -> Option<Receiver<VolumeKeySignal>> {
                         }
                         let keycode = event.get_integer_value_field(EventField::KEYBOARD_EVENT_KEYCODE);
                         let repeat = event.get_integer_value_field(EventField::KEYBOARD_EVENT_AUTOREPEAT);
-                        let signal = match keycode {
-                            key if key == MEDIA_KEYCODE_VOLUME_UP || key == FKEYCODE_F12 => Some(VolumeKeySignal::Up),
-                            key if key == MEDIA_KEYCODE_VOLUME_DOWN || key == FKEYCODE_F11 => {
-                                Some(VolumeKeySignal::Down)
-                            }
-                            key if key == MEDIA_KEYCODE_VOLUME_MUTE || key == FKEYCODE_F10 => {
-                                Some(VolumeKeySignal::Mute)
-                            }
-                            _ => None,
-                        };
+                        let signal = key_map.keycode_signal(keycode);
                         volume_key_debug(format!(
                             "event: type={:?} keycode={} autorepeat={} mapped={:?}",
                             event_type, keycode, repeat, signal
@@ -593,7 +674,9 @@ pub fn start_volume_key_listener() -> Option<Receiver<VolumeKeySignal>> {
                                 "keydown mapped {:?}; suppress_until_ms={}",
                                 signal, suppress_until_ms
                             ));
-                            if tx.send(signal).is_err() {
+                            if !suppress_state.claim_signal(signal, now_unix_millis()) {
+                                volume_key_debug(format!("keydown {signal:?} already sent by the hid listener"));
+                            } else if tx.send(signal).is_err() {
                                 volume_key_debug("receiver dropped; stopping volume key listener callback");
                             }
                         }
@@ -617,7 +700,10 @@ pub fn start_volume_key_listener() -> Option<Receiver<VolumeKeySignal>> {
                 }
                 tap.enable();
                 volume_key_debug("volume key listener runloop active");
-                CFRunLoop::run_current();
+                run_until_stopped(&stopped);
+                unsafe {
+                    run_loop.remove_source(&loop_source, kCFRunLoopCommonModes);
+                }
                 volume_key_debug("volume key listener runloop exited");
             });
         if let Err(err) = res {
@@ -633,11 +719,16 @@ pub fn start_volume_key_listener() -> Option<Receiver<VolumeKeySignal>> {
     let (tx, rx) = channel::<VolumeKeySignal>();
     let suppress_state = Arc::new(SuppressState {
         suppress_until_ms: AtomicU64::new(0),
+        last_signal: Mutex::new(None),
     });
-    let hid_started = start_hid_listener(tx.clone(), suppress_state.clone());
-    let keydown_tap_started = start_keydown_tap_listener(tx, suppress_state.clone());
+    let stopped = Arc::new(AtomicBool::new(false));
+    // Either source may be left without keys to listen for, e.g. to ignore the F-keys
+    let hid_started = !key_map.hid_usages.is_empty()
+        && start_hid_listener(tx.clone(), key_map.clone(), suppress_state.clone(), stopped.clone());
+    let keydown_tap_started = !key_map.keycodes.is_empty()
+        && start_keydown_tap_listener(tx, key_map, suppress_state.clone(), stopped.clone());
     let suppressor_started = if hid_started || keydown_tap_started {
-        start_system_defined_suppressor_listener(suppress_state)
+        start_system_defined_suppressor_listener(suppress_state, stopped.clone())
     } else {
         false
     };
@@ -647,5 +738,5 @@ pub fn start_volume_key_listener() -> Option<Receiver<VolumeKeySignal>> {
     if !hid_started && !keydown_tap_started {
         return None;
     }
-    Some(rx)
+    Some(VolumeKeyListener { rx, stopped })
 }
@@ -89,6 +89,21 @@ fn media_remote_queue() -> *mut dispatch_queue_s {
     }) as *mut dispatch_queue_s
 }
 
+/// Transport commands, numbered as MediaRemote's `MRMediaRemoteCommand`.
+#[derive(Clone, Copy, Debug)]
+pub enum MediaRemoteCommand {
+    TogglePlayPause = 2,
+    NextTrack = 4,
+    PreviousTrack = 5,
+}
+
+/// Send a transport command to the now playing app. Returns whether MediaRemote accepted it.
+pub fn send_command(command: MediaRemoteCommand) -> bool {
+    let sent = unsafe { MRMediaRemoteSendCommand(command as u32, ptr::null()) };
+    mr_debug!("send_command({:?}) -> {}", command, sent);
+    sent
+}
+
 fn query_is_playing() -> Option<bool> {
     let result = safely_dispatch_and_wait!(
         |is_playing: c_int| Some(is_playing != 0),
@@ -306,4 +321,6 @@ unsafe extern "C" {
     fn MRMediaRemoteRegisterForNowPlayingNotifications(queue: *mut dispatch_queue_s);
 
     fn MRMediaRemoteUnregisterForNowPlayingNotifications();
+
+    fn MRMediaRemoteSendCommand(command: u32, user_info: *const c_void) -> bool;
 }
@@ -1,108 +1,87 @@
-use draconis::{init_static_plugins, initialize_plugin_manager, shutdown_plugin_manager, CacheManager, Plugin};
+use std::{sync::Arc, time::Duration};
 
 #[derive(PartialEq)]
 pub struct Media {
     pub title: String,
     pub artist: String,
+    pub album: String,
+    /// How far into the track playback is, if the player reports it.
+    pub position: Option<Duration>,
+    pub length: Option<Duration>,
+    /// Where to load album art from, as a `file://` or `http(s)://` URL.
+    pub art_url: Option<String>,
+    /// Album art the player hands over directly rather than by URL, already decoded.
+    pub art: Option<Arc<ggoled_lib::Bitmap>>,
+}
+impl Media {
+    /// Whether both are the same track, regardless of playback position.
+    pub fn same_track(&self, other: &Media) -> bool {
+        self.title == other.title && self.artist == other.artist && self.album == other.album
+    }
 }
 
-#[cfg(target_os = "macos")]
-#[derive(Clone, Copy, Debug)]
+#[cfg(any(target_os = "macos", target_os = "windows"))]
+#[derive(Clone, Copy, Debug, PartialEq)]
 pub enum VolumeKeySignal {
     Up,
     Down,
     Mute,
 }
 
-#[derive(Clone, Copy)]
-pub struct PlatformCapabilities {
-    pub media: bool,
-    pub idle_timeout: bool,
-    pub autostart: bool,
-}
-
-pub struct MediaControl {
-    plugin: Option<Plugin>,
-    cache: CacheManager,
+/// Key codes and HID consumer usages that the volume key listener treats as each volume key.
+#[cfg(any(target_os = "macos", target_os = "windows"))]
+#[derive(Clone, Debug, Default)]
+pub struct VolumeKeyMap {
+    pub keycodes: Vec<(i64, VolumeKeySignal)>,
+    // Only the macOS listener sees HID usages
+    #[cfg_attr(target_os = "windows", allow(dead_code))]
+    pub hid_usages: Vec<(u32, VolumeKeySignal)>,
 }
-
-impl MediaControl {
-    pub fn new() -> MediaControl {
-        initialize_plugin_manager();
-        let count = init_static_plugins();
-        if count == 0 {
-            tracing::warn!("No static plugins registered");
-        }
-
-        let mut cache = CacheManager::new();
-        let mut plugin = Plugin::new("NowPlayingPlugin").ok();
-
-        if let Some(ref mut p) = plugin {
-            if let Err(e) = p.initialize(&mut cache) {
-                tracing::warn!("Failed to initialize NowPlayingPlugin: {:?}", e);
-                plugin = None;
-            }
-        }
-
-        if plugin.is_none() {
-            tracing::warn!("Failed to load NowPlayingPlugin");
-        }
-
-        MediaControl { plugin, cache }
+#[cfg(any(target_os = "macos", target_os = "windows"))]
+impl VolumeKeyMap {
+    pub fn keycode_signal(&self, keycode: i64) -> Option<VolumeKeySignal> {
+        self.keycodes
+            .iter()
+            .find(|(code, _)| *code == keycode)
+            .map(|(_, signal)| *signal)
     }
-
-    pub fn get_media(&mut self, _include_paused: bool) -> Option<Media> {
-        let plugin = self.plugin.as_mut()?;
-
-        if let Err(e) = plugin.collect_data(&mut self.cache) {
-            let last_error = plugin.get_last_error();
-            tracing::warn!("Failed to collect plugin data: {:?} (last_error: {:?})", e, last_error);
-            return None;
-        }
-
-        let fields = plugin.get_fields().ok()?;
-
-        tracing::debug!("Plugin fields: {:?}", fields);
-
-        let title = fields.get("title")?.clone();
-        let artist = fields.get("artist").cloned().unwrap_or_default();
-
-        if title.is_empty() {
-            return None;
-        }
-
-        Some(Media { title, artist })
+    #[cfg_attr(target_os = "windows", allow(dead_code))]
+    pub fn hid_usage_signal(&self, usage: u32) -> Option<VolumeKeySignal> {
+        self.hid_usages
+            .iter()
+            .find(|(code, _)| *code == usage)
+            .map(|(_, signal)| *signal)
     }
 }
 
-impl Drop for MediaControl {
-    fn drop(&mut self) {
-        shutdown_plugin_manager();
-    }
-}
-
-pub fn capabilities() -> PlatformCapabilities {
-    PlatformCapabilities {
-        media: true,
-        idle_timeout: false,
-        autostart: false,
-    }
+#[derive(Clone, Copy)]
+pub struct PlatformCapabilities {
+    pub media: bool,
+    /// Whether `MediaControl` can play, pause and skip tracks.
+    pub media_control: bool,
+    pub idle_timeout: bool,
+    pub autostart: bool,
 }
 
-pub fn set_autostart(_enabled: bool) {}
-pub fn get_autostart() -> bool {
-    false
-}
-pub fn get_idle_seconds() -> usize {
-    0
-}
+#[cfg(target_os = "linux")]
+mod linux;
+#[cfg(target_os = "linux")]
+pub use linux::*;
 
 #[cfg(target_os = "macos")]
-pub fn start_volume_key_listener() -> Option<std::sync::mpsc::Receiver<VolumeKeySignal>> {
-    None
-}
-
+mod macos;
 #[cfg(target_os = "macos")]
-pub fn ensure_accessibility_permission(_prompt: bool) -> bool {
-    true
-}
+mod macos_mediaremote;
+#[cfg(target_os = "macos")]
+pub use macos::*;
+
+#[cfg(target_os = "windows")]
+mod windows;
+#[cfg(target_os = "windows")]
+pub use windows::*;
+
+// Elsewhere media comes from draconis' NowPlaying plugin
+#[cfg(not(any(target_os = "linux", target_os = "macos", target_os = "windows")))]
+mod draconis;
+#[cfg(not(any(target_os = "linux", target_os = "macos", target_os = "windows")))]
+pub use self::draconis::*;
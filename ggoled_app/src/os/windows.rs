@@ -1,11 +1,34 @@
-use super::{Media, PlatformCapabilities};
-use std::mem::size_of;
-use windows::Media::Control::{
-    GlobalSystemMediaTransportControlsSessionManager, GlobalSystemMediaTransportControlsSessionPlaybackStatus,
+use super::{Media, PlatformCapabilities, VolumeKeyMap, VolumeKeySignal};
+use anyhow::{bail, Context};
+use ggoled_draw::album_art_from_memory;
+use ggoled_lib::Bitmap;
+use std::{
+    cell::RefCell,
+    mem::size_of,
+    sync::{
+        mpsc::{channel, sync_channel, Receiver, Sender, TryIter},
+        Arc,
+    },
+};
+use tracing::{debug, warn};
+use windows::{
+    Media::Control::{
+        GlobalSystemMediaTransportControlsSession, GlobalSystemMediaTransportControlsSessionManager,
+        GlobalSystemMediaTransportControlsSessionMediaProperties,
+        GlobalSystemMediaTransportControlsSessionPlaybackStatus,
+    },
+    Storage::Streams::DataReader,
 };
 use windows_sys::Win32::{
-    System::SystemInformation::GetTickCount,
-    UI::Input::KeyboardAndMouse::{GetLastInputInfo, LASTINPUTINFO},
+    Foundation::{LPARAM, LRESULT, WPARAM},
+    System::{LibraryLoader::GetModuleHandleW, SystemInformation::GetTickCount, Threading::GetCurrentThreadId},
+    UI::{
+        Input::KeyboardAndMouse::{GetLastInputInfo, LASTINPUTINFO},
+        WindowsAndMessaging::{
+            CallNextHookEx, GetMessageW, PostThreadMessageW, SetWindowsHookExW, UnhookWindowsHookEx, HC_ACTION,
+            KBDLLHOOKSTRUCT, MSG, WH_KEYBOARD_LL, WM_KEYDOWN, WM_QUIT, WM_SYSKEYDOWN,
+        },
+    },
 };
 
 const RUN_KEY: &str = "Software\\Microsoft\\Windows\\CurrentVersion\\Run";
@@ -14,6 +37,7 @@ const APP_NAME: &str = "GGOLED";
 pub fn capabilities() -> PlatformCapabilities {
     PlatformCapabilities {
         media: true,
+        media_control: true,
         idle_timeout: true,
         autostart: true,
     }
@@ -80,8 +104,30 @@ pub fn get_autostart() -> bool {
     }
 }
 
+// Reads the thumbnail stream, which players without album art leave empty
+fn read_thumbnail(media: &GlobalSystemMediaTransportControlsSessionMediaProperties) -> anyhow::Result<Option<Bitmap>> {
+    let Ok(thumbnail) = media.Thumbnail() else {
+        return Ok(None);
+    };
+    let stream = thumbnail.OpenReadAsync()?.join()?;
+    let size = stream.Size()? as u32;
+    if size == 0 {
+        return Ok(None);
+    }
+    let reader = DataReader::CreateDataReader(&stream)?;
+    reader.LoadAsync(size)?.join()?;
+    let mut buf = vec![0; size as usize];
+    reader.ReadBytes(&mut buf)?;
+    Ok(Some(album_art_from_memory(&buf, crate::ALBUM_ART_SIZE)?))
+}
+
+// Title, artist and album
+type Track = (String, String, String);
+
 pub struct MediaControl {
     mgr: Option<GlobalSystemMediaTransportControlsSessionManager>,
+    // Thumbnail of the last track, so it's only read again when the track changes
+    art: Option<(Track, Option<Arc<Bitmap>>)>,
 }
 impl MediaControl {
     pub fn new() -> MediaControl {
@@ -89,9 +135,9 @@ impl MediaControl {
             .map(|req| req.join().ok())
             .ok()
             .flatten();
-        MediaControl { mgr }
+        MediaControl { mgr, art: None }
     }
-    pub fn get_media(&self, include_paused: bool) -> Option<Media> {
+    pub fn get_media(&mut self, include_paused: bool) -> Option<Media> {
         if let Some(mgr) = &self.mgr {
             (|| {
                 let session = mgr.GetCurrentSession()?;
@@ -101,9 +147,30 @@ impl MediaControl {
                 if allowed {
                     let request = session.TryGetMediaPropertiesAsync()?;
                     let media = request.join()?;
+                    let title = media.Title()?.to_string_lossy();
+                    let artist = media.Artist()?.to_string_lossy();
+                    let album = media.AlbumTitle()?.to_string_lossy();
+                    let track = (title.clone(), artist.clone(), album.clone());
+                    let art = match &self.art {
+                        Some((last_track, art)) if *last_track == track => art.clone(),
+                        _ => {
+                            let art = read_thumbnail(&media)
+                                .inspect_err(|err| debug!(?err, "failed to read media thumbnail"))
+                                .ok()
+                                .flatten()
+                                .map(Arc::new);
+                            self.art = Some((track, art.clone()));
+                            art
+                        }
+                    };
                     anyhow::Ok(Some(Media {
-                        title: media.Title()?.to_string_lossy(),
-                        artist: media.Artist()?.to_string_lossy(),
+                        title,
+                        artist,
+                        album,
+                        position: None,
+                        length: None,
+                        art_url: None,
+                        art,
                     }))
                 } else {
                     anyhow::Ok(None)
@@ -115,6 +182,27 @@ impl MediaControl {
             None
         }
     }
+
+    fn session(&self) -> anyhow::Result<GlobalSystemMediaTransportControlsSession> {
+        let mgr = self.mgr.as_ref().context("no media session manager")?;
+        Ok(mgr.GetCurrentSession()?)
+    }
+    // The Try* commands report whether the player accepted them
+    fn expect_accepted(accepted: bool, command: &str) -> anyhow::Result<()> {
+        if !accepted {
+            bail!("the player didn't accept {command}");
+        }
+        Ok(())
+    }
+    pub fn play_pause(&self) -> anyhow::Result<()> {
+        Self::expect_accepted(self.session()?.TryTogglePlayPauseAsync()?.join()?, "play/pause")
+    }
+    pub fn next(&self) -> anyhow::Result<()> {
+        Self::expect_accepted(self.session()?.TrySkipNextAsync()?.join()?, "next")
+    }
+    pub fn previous(&self) -> anyhow::Result<()> {
+        Self::expect_accepted(self.session()?.TrySkipPreviousAsync()?.join()?, "previous")
+    }
 }
 
 pub fn get_idle_seconds() -> usize {
@@ -130,3 +218,76 @@ pub fn get_idle_seconds() -> usize {
         }
     }
 }
+
+// Windows doesn't gate low-level keyboard hooks behind a permission like macOS does
+pub fn ensure_accessibility_permission(_prompt: bool) -> bool {
+    true
+}
+
+/// Running volume key listener. Dropping it stops the hook thread and removes its keyboard hook.
+pub struct VolumeKeyListener {
+    rx: Receiver<VolumeKeySignal>,
+    thread_id: u32,
+}
+impl VolumeKeyListener {
+    /// Volume keys pressed since the last call.
+    pub fn signals(&self) -> TryIter<'_, VolumeKeySignal> {
+        self.rx.try_iter()
+    }
+}
+impl Drop for VolumeKeyListener {
+    fn drop(&mut self) {
+        unsafe { PostThreadMessageW(self.thread_id, WM_QUIT, 0, 0) };
+    }
+}
+
+thread_local! {
+    // The hook procedure runs on the thread that installed it, so its state lives there
+    static VOLUME_KEY_HOOK: RefCell<Option<(VolumeKeyMap, Sender<VolumeKeySignal>)>> = const { RefCell::new(None) };
+}
+
+unsafe extern "system" fn volume_key_hook_proc(code: i32, wparam: WPARAM, lparam: LPARAM) -> LRESULT {
+    if code == HC_ACTION as i32 && (wparam == WM_KEYDOWN as WPARAM || wparam == WM_SYSKEYDOWN as WPARAM) {
+        let vk_code = (*(lparam as *const KBDLLHOOKSTRUCT)).vkCode;
+        VOLUME_KEY_HOOK.with_borrow(|hook| {
+            if let Some((key_map, tx)) = hook {
+                if let Some(signal) = key_map.keycode_signal(vk_code as i64) {
+                    _ = tx.send(signal);
+                }
+            }
+        });
+    }
+    // Keys are passed on, so Windows still changes its own volume as well
+    CallNextHookEx(std::ptr::null_mut(), code, wparam, lparam)
+}
+
+pub fn start_volume_key_listener(key_map: VolumeKeyMap) -> Option<VolumeKeyListener> {
+    let (tx, rx) = channel();
+    let (started_tx, started_rx) = sync_channel(1);
+    let spawned = std::thread::Builder::new()
+        .name("ggoled-volume-keys".to_string())
+        .spawn(move || unsafe {
+            VOLUME_KEY_HOOK.set(Some((key_map, tx)));
+            let hook = SetWindowsHookExW(
+                WH_KEYBOARD_LL,
+                Some(volume_key_hook_proc),
+                GetModuleHandleW(std::ptr::null()),
+                0,
+            );
+            if hook.is_null() {
+                _ = started_tx.send(None);
+                return;
+            }
+            _ = started_tx.send(Some(GetCurrentThreadId()));
+            // Low-level hooks are called from this thread's message loop, which runs until the listener is dropped
+            let mut msg: MSG = std::mem::zeroed();
+            while GetMessageW(&mut msg, std::ptr::null_mut(), 0, 0) > 0 {}
+            UnhookWindowsHookEx(hook);
+        });
+    if let Err(err) = spawned {
+        warn!(?err, "failed to spawn volume key listener thread");
+        return None;
+    }
+    let thread_id = started_rx.recv().ok().flatten()?;
+    Some(VolumeKeyListener { rx, thread_id })
+}
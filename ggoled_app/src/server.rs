@@ -0,0 +1,66 @@
+use ggoled_draw::{bitmap_to_png, FrameSnapshot};
+use std::{
+    io::{BufRead, BufReader, Write},
+    net::{TcpListener, TcpStream},
+    time::{Duration, Instant},
+};
+use tracing::debug;
+
+// Requests arriving faster than this get the previously encoded frame, so polling clients can't add much load
+const MIN_ENCODE_INTERVAL: Duration = Duration::from_millis(250);
+const CLIENT_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// Serve the current screen contents as `GET /screen.png` on `addr`, from a background thread.
+pub fn spawn(addr: &str, snapshot: FrameSnapshot) -> anyhow::Result<()> {
+    let listener = TcpListener::bind(addr)?;
+    std::thread::Builder::new()
+        .name("ggoled-server".to_string())
+        .spawn(move || {
+            let mut cache: Option<(Instant, Vec<u8>)> = None;
+            for stream in listener.incoming() {
+                let Ok(stream) = stream else {
+                    continue;
+                };
+                if let Err(err) = handle_request(stream, &snapshot, &mut cache) {
+                    debug!(?err, "screen server request failed");
+                }
+            }
+        })?;
+    Ok(())
+}
+
+fn handle_request(
+    mut stream: TcpStream,
+    snapshot: &FrameSnapshot,
+    cache: &mut Option<(Instant, Vec<u8>)>,
+) -> anyhow::Result<()> {
+    stream.set_read_timeout(Some(CLIENT_TIMEOUT))?;
+    stream.set_write_timeout(Some(CLIENT_TIMEOUT))?;
+    let mut request_line = String::new();
+    BufReader::new(&stream).read_line(&mut request_line)?;
+    let mut parts = request_line.split_whitespace();
+    if parts.next() != Some("GET") || parts.next() != Some("/screen.png") {
+        stream.write_all(b"HTTP/1.1 404 Not Found\r\nContent-Length: 0\r\nConnection: close\r\n\r\n")?;
+        return Ok(());
+    }
+
+    if cache
+        .as_ref()
+        .is_none_or(|(encoded_at, _)| encoded_at.elapsed() >= MIN_ENCODE_INTERVAL)
+    {
+        if let Some(frame) = snapshot.latest() {
+            *cache = Some((Instant::now(), bitmap_to_png(&frame)?));
+        }
+    }
+    let Some((_, png)) = cache.as_ref() else {
+        stream.write_all(b"HTTP/1.1 503 Service Unavailable\r\nContent-Length: 0\r\nConnection: close\r\n\r\n")?;
+        return Ok(());
+    };
+    write!(
+        stream,
+        "HTTP/1.1 200 OK\r\nContent-Type: image/png\r\nContent-Length: {}\r\nCache-Control: no-store\r\nConnection: close\r\n\r\n",
+        png.len()
+    )?;
+    stream.write_all(png)?;
+    Ok(())
+}
@@ -0,0 +1,56 @@
+use ggoled_lib::Bitmap;
+use sysinfo::{MemoryRefreshKind, System};
+
+pub const BARS_HEIGHT: usize = 7;
+pub const GRAPH_HEIGHT: usize = 10;
+const BARS_GAP: usize = 6;
+
+/// CPU and RAM usage, both in percent.
+pub struct StatsSample {
+    pub cpu: f32,
+    pub ram: f32,
+}
+
+pub struct SystemStats {
+    sys: System,
+}
+impl SystemStats {
+    pub fn new() -> SystemStats {
+        let mut sys = System::new();
+        // CPU usage is computed between two refreshes, so take the first one right away
+        sys.refresh_cpu_usage();
+        SystemStats { sys }
+    }
+
+    pub fn sample(&mut self) -> StatsSample {
+        self.sys.refresh_cpu_usage();
+        self.sys
+            .refresh_memory_specifics(MemoryRefreshKind::nothing().with_ram());
+        let total = self.sys.total_memory();
+        let ram = if total == 0 {
+            0.0
+        } else {
+            self.sys.used_memory() as f32 * 100.0 / total as f32
+        };
+        StatsSample {
+            cpu: self.sys.global_cpu_usage(),
+            ram,
+        }
+    }
+}
+
+/// Render CPU (left) and RAM (right) usage as two horizontal bars.
+pub fn render_bars(width: usize, sample: &StatsSample) -> Bitmap {
+    let mut bitmap = Bitmap::new(width, BARS_HEIGHT, false);
+    let bar_w = width.saturating_sub(BARS_GAP) / 2;
+    if bar_w < 4 {
+        return bitmap;
+    }
+    for (i, percent) in [sample.cpu, sample.ram].into_iter().enumerate() {
+        let x = (i * (bar_w + BARS_GAP)) as isize;
+        bitmap.draw_rect(x, 0, bar_w, BARS_HEIGHT, true);
+        let fill_w = ((bar_w - 4) as f32 * percent.clamp(0.0, 100.0) / 100.0).round() as usize;
+        bitmap.fill_rect(x + 2, 2, fill_w, BARS_HEIGHT - 4, true);
+    }
+    bitmap
+}
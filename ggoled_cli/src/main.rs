@@ -1,12 +1,16 @@
 use clap::{Parser, ValueEnum};
 use core::str;
-use ggoled_draw::bitmap_from_memory;
+use ggoled_draw::bitmap_to_png_scaled;
 use ggoled_draw::decode_frames;
+use ggoled_draw::frames_from_memory;
 use ggoled_draw::DrawDevice;
 use ggoled_draw::TextOverflowMode;
+use ggoled_lib::volume_from_percent;
 use ggoled_lib::Bitmap;
 use ggoled_lib::Device;
 use spin_sleep::sleep;
+use std::path::{Path, PathBuf};
+use std::process::exit;
 use std::sync::Arc;
 use std::time::Instant;
 use std::{
@@ -16,6 +20,9 @@ use std::{
     time::Duration,
 };
 
+// How often `events` reads devices that don't have a separate handle for events
+const EVENTS_POLL_INTERVAL: Duration = Duration::from_millis(20);
+
 #[derive(Clone, Copy)]
 enum DrawPos {
     Coord(isize),
@@ -64,6 +71,7 @@ struct DrawArgs {
     #[arg(
         short = 'x',
         long,
+        alias = "x",
         help = "Screen X offset for draw commands",
         default_value = "center"
     )]
@@ -72,10 +80,33 @@ struct DrawArgs {
     #[arg(
         short = 'y',
         long,
+        alias = "y",
         help = "Screen Y offset for draw commands",
         default_value = "center"
     )]
     screen_y: DrawPos,
+
+    #[arg(long, help = "Also save the frame left on the screen as a PNG file")]
+    save_frame: Option<PathBuf>,
+
+    #[arg(long, help = "Size of each pixel in the saved frame", default_value = "4")]
+    save_scale: usize,
+}
+impl DrawArgs {
+    fn save(&self, frame: &Bitmap) {
+        let Some(path) = &self.save_frame else {
+            return;
+        };
+        if let Err(err) = save_png(path, frame, self.save_scale) {
+            eprintln!("Failed to save frame to {}: {err}", path.display());
+            exit(1);
+        }
+    }
+}
+
+fn save_png(path: &Path, frame: &Bitmap, scale: usize) -> anyhow::Result<()> {
+    std::fs::write(path, bitmap_to_png_scaled(frame, scale)?)?;
+    Ok(())
 }
 
 #[derive(clap::Args)]
@@ -115,11 +146,19 @@ enum Args {
         delimiter: Option<String>,
     },
 
-    #[command(about = "Draw an image")]
+    #[command(about = "Draw an image, or play a gif", visible_alias = "draw")]
     Img {
         #[command(flatten)]
         image_args: ImageArgs,
 
+        #[arg(
+            short = 'l',
+            long,
+            help = "Amount of repetitions for gifs, or 0 for infinite",
+            default_value = "1"
+        )]
+        loops: usize,
+
         #[arg(help = "Image path, or - for stdin", index = 1)]
         path: String,
     },
@@ -154,14 +193,92 @@ enum Args {
         value: u8,
     },
 
+    #[command(about = "Set base station volume")]
+    Volume {
+        #[arg(help = "Volume in percent, 0-100", index = 1, value_parser = clap::value_parser!(u8).range(0..=100))]
+        percent: u8,
+    },
+
+    #[command(about = "Show info about the connected device")]
+    Info,
+
+    #[command(about = "Print events from the device as they happen, including unrecognized ones")]
+    Events,
+
+    #[command(about = "Show test patterns for checking the screen")]
+    TestPattern {
+        #[arg(short = 'd', long, help = "Seconds to show each pattern", default_value = "2", value_parser = parse_seconds)]
+        delay: Duration,
+    },
+
+    #[command(about = "List the devices ggoled supports")]
+    KnownDevices,
+
     #[command(about = "Dump devices list to stdout", hide = true)]
     DumpDevices,
 }
 
+// Parses a non-negative number of seconds, possibly fractional
+fn parse_seconds(arg: &str) -> Result<Duration, String> {
+    let secs: f32 = arg.parse().map_err(|err| format!("{err}"))?;
+    Duration::try_from_secs_f32(secs).map_err(|_| format!("{arg} is not a valid number of seconds"))
+}
+
+// Position of a bitmap on the screen, centered unless given
+fn draw_pos(dev: &Device, bitmap: &Bitmap, draw_args: &DrawArgs) -> (isize, isize) {
+    let cx = (dev.width as isize - bitmap.w as isize) / 2;
+    let cy = (dev.height as isize - bitmap.h as isize) / 2;
+    (
+        draw_args.screen_x.to_option().unwrap_or(cx),
+        draw_args.screen_y.to_option().unwrap_or(cy),
+    )
+}
+
+// The whole screen with `bitmap` drawn where `draw_pos` puts it
+fn screen_frame(dev: &Device, bitmap: &Bitmap, draw_args: &DrawArgs) -> Bitmap {
+    let mut screen = Bitmap::new(dev.width, dev.height, false);
+    let (x, y) = draw_pos(dev, bitmap, draw_args);
+    screen.blit(bitmap, x, y, true);
+    screen
+}
+
+fn play_frames(dev: &mut Device, frames: &[(Arc<Bitmap>, Duration)], draw_args: &DrawArgs, loops: usize) {
+    let draw_animation = || {
+        for (bitmap, delay) in frames {
+            let now_time = Instant::now();
+            let next_frame = now_time + *delay;
+            let (x, y) = draw_pos(dev, bitmap, draw_args);
+            dev.draw(bitmap, x, y).unwrap();
+            if now_time < next_frame {
+                sleep(next_frame.duration_since(Instant::now()));
+            } else {
+                println!("fell behind - framerate too fast");
+            }
+        }
+    };
+    if loops == 0 {
+        loop {
+            draw_animation();
+        }
+    } else {
+        for _ in 0..loops {
+            draw_animation();
+        }
+    }
+    if let Some((bitmap, _)) = frames.last() {
+        draw_args.save(&screen_frame(dev, bitmap, draw_args));
+    }
+}
+
 fn main() {
     let args = Args::parse();
-    #[allow(clippy::single_match)]
     match args {
+        Args::KnownDevices => {
+            for (vendor_id, product_id, name) in ggoled_lib::known_devices() {
+                println!("{vendor_id:04x}:{product_id:04x} {name}");
+            }
+            return;
+        }
         Args::DumpDevices => {
             Device::dump_devices();
             return;
@@ -169,7 +286,10 @@ fn main() {
         _ => {} // Handled later after device connection
     }
 
-    let dev = Device::connect().unwrap();
+    let mut dev = Device::connect().unwrap_or_else(|err| {
+        eprintln!("Failed to connect to device: {err}");
+        exit(1);
+    });
     match args {
         Args::Clear => dev.draw(&Bitmap::new(dev.width, dev.height, false), 0, 0).unwrap(),
         Args::Fill => dev.draw(&Bitmap::new(dev.width, dev.height, true), 0, 0).unwrap(),
@@ -216,20 +336,39 @@ fn main() {
                     );
                 }
             }
+            // Stopping draws one last frame, which is what stays on the screen
+            let snapshot = dev.frame_snapshot();
+            dev.stop();
+            if let Some(frame) = snapshot.latest() {
+                draw_args.save(&frame);
+            }
         }
-        Args::Img { path, image_args } => {
-            let bitmap = if path == "-" {
+        Args::Img {
+            path,
+            image_args,
+            loops,
+        } => {
+            let mut frames = if path == "-" {
                 let mut buf = Vec::<u8>::new();
                 stdin().read_to_end(&mut buf).expect("Failed to read from stdin");
-                Arc::new(bitmap_from_memory(&buf, image_args.threshold).expect("Failed to read image from stdin"))
+                frames_from_memory(&buf, image_args.threshold).expect("Failed to read image from stdin")
             } else {
-                let mut frames = decode_frames(&path, image_args.threshold);
-                if frames.len() != 1 {
-                    eprintln!("img only supports images with single frame");
-                }
-                frames.swap_remove(0).bitmap
+                decode_frames(&path, image_args.threshold)
             };
-            dev.draw(&bitmap, 0, 0).unwrap();
+            if frames.len() == 1 {
+                let bitmap = frames.swap_remove(0).bitmap;
+                let (x, y) = draw_pos(&dev, &bitmap, &image_args.draw_args);
+                dev.draw(&bitmap, x, y).unwrap();
+                image_args
+                    .draw_args
+                    .save(&screen_frame(&dev, &bitmap, &image_args.draw_args));
+            } else {
+                let frames: Vec<_> = frames
+                    .into_iter()
+                    .map(|frame| (frame.bitmap, frame.delay.unwrap_or(Duration::from_secs(1))))
+                    .collect();
+                play_frames(&mut dev, &frames, &image_args.draw_args, loops);
+            }
         }
         Args::Anim {
             framerate,
@@ -254,37 +393,48 @@ fn main() {
                     })
                 })
                 .collect();
-            let mut frame_idx = 0;
-            let mut draw_animation = || {
-                for (bitmap, delay) in &bitmaps {
-                    let now_time = Instant::now();
-                    let next_frame = now_time + *delay;
-                    let cx = (dev.width as isize - bitmap.w as isize) / 2;
-                    let cy = (dev.height as isize - bitmap.h as isize) / 2;
-                    let x = image_args.draw_args.screen_x.to_option().unwrap_or(cx);
-                    let y = image_args.draw_args.screen_y.to_option().unwrap_or(cy);
-                    dev.draw(bitmap, x, y).unwrap();
-                    frame_idx += 1;
-                    if now_time < next_frame {
-                        sleep(next_frame.duration_since(Instant::now()));
-                    } else {
-                        println!("fell behind - framerate too fast");
+            play_frames(&mut dev, &bitmaps, &image_args.draw_args, loops);
+        }
+        Args::Brightness { value } => {
+            if let Err(err) = dev.set_brightness(value) {
+                eprintln!("Failed to set brightness: {err}");
+                exit(1);
+            }
+        }
+        Args::Volume { percent } => {
+            if let Err(err) = dev.set_volume(volume_from_percent(percent)) {
+                eprintln!("Failed to set volume: {err}");
+                exit(1);
+            }
+        }
+        Args::Info => {
+            let info = dev.info();
+            println!("product: {}", info.product);
+            println!("vendor id: {:#06x}", info.vendor_id);
+            println!("product id: {:#06x}", info.product_id);
+            println!("serial: {}", info.serial.as_deref().unwrap_or("unknown"));
+            println!("interface: {}", info.interface);
+            println!("firmware: {}", info.firmware.as_deref().unwrap_or("unknown"));
+        }
+        Args::Events => {
+            dev.set_report_unknown_events(true);
+            loop {
+                if dev.has_info_handle() {
+                    if let Some(event) = dev.poll_event().unwrap() {
+                        println!("{event:x?}");
                     }
-                }
-            };
-            if loops == 0 {
-                loop {
-                    draw_animation();
-                }
-            } else {
-                for _ in 0..loops {
-                    draw_animation();
+                } else {
+                    // Reading the shared handle doesn't block, so wait a bit between reads
+                    for event in dev.get_events().unwrap() {
+                        println!("{event:x?}");
+                    }
+                    sleep(EVENTS_POLL_INTERVAL);
                 }
             }
         }
-        Args::Brightness { value } => {
-            dev.set_brightness(value).unwrap();
+        Args::TestPattern { delay } => {
+            dev.draw_test_pattern(delay).unwrap();
         }
-        Args::DumpDevices => {} // Handled earlier before device connection
+        Args::KnownDevices | Args::DumpDevices => {} // Handled earlier before device connection
     }
 }
@@ -0,0 +1,43 @@
+// Shows a clock (UTC) on the display until interrupted, dissolving between seconds.
+
+use ggoled_draw::{DrawDevice, DrawLayer, ShiftMode, TransitionConfig, TransitionKind};
+use ggoled_lib::{Bitmap, Device};
+use std::{
+    sync::Arc,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+fn main() -> anyhow::Result<()> {
+    let mut dev = DrawDevice::builder(Device::connect()?)
+        .shift_mode(ShiftMode::Simple)
+        .playing()
+        .build();
+
+    let render = |dev: &DrawDevice, secs: u64| -> Arc<Bitmap> {
+        let text = format!("{:02}:{:02}:{:02}", secs / 3600 % 24, secs / 60 % 60, secs % 60);
+        Arc::new(dev.texter.render_lines(&text).swap_remove(0))
+    };
+    let mut last_secs = SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs();
+    let bitmap = render(&dev, last_secs);
+    let (x, y) = dev.center_bitmap(&bitmap);
+    let layer = dev.add_layer(DrawLayer::Image { bitmap, x, y });
+    dev.set_layer_transition(
+        layer,
+        Some(TransitionConfig {
+            kind: TransitionKind::Dissolve,
+            frames: 8,
+        }),
+    );
+    loop {
+        let secs = SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs();
+        if secs != last_secs {
+            last_secs = secs;
+            let bitmap = render(&dev, secs);
+            dev.set_layer_bitmap(layer, bitmap);
+        }
+        while let Some(event) = dev.try_event() {
+            println!("{event:?}");
+        }
+        std::thread::sleep(Duration::from_millis(100));
+    }
+}
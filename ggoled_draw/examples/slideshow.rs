@@ -0,0 +1,38 @@
+// Cycles through the images given as arguments, showing each for a few seconds.
+
+use ggoled_draw::{bitmap_from_memory, DrawDevice, DrawLayer};
+use ggoled_lib::Device;
+use std::{sync::Arc, time::Duration};
+
+const SLIDE_DUR: Duration = Duration::from_secs(5);
+const THRESHOLD: u8 = 100;
+
+fn main() -> anyhow::Result<()> {
+    let bitmaps = std::env::args()
+        .skip(1)
+        .map(|path| anyhow::Ok(Arc::new(bitmap_from_memory(&std::fs::read(path)?, THRESHOLD)?)))
+        .collect::<anyhow::Result<Vec<_>>>()?;
+    if bitmaps.is_empty() {
+        anyhow::bail!("usage: slideshow <image>...");
+    }
+
+    let mut dev = DrawDevice::new(Device::connect()?, 30);
+    dev.play();
+
+    let mut layer = None;
+    for bitmap in bitmaps.iter().cycle() {
+        let (x, y) = dev.center_bitmap(bitmap);
+        layer = Some(dev.transact_layers(|txn| {
+            if let Some(id) = layer {
+                txn.remove_layer(id);
+            }
+            txn.add_layer(DrawLayer::Image {
+                bitmap: bitmap.clone(),
+                x,
+                y,
+            })
+        }));
+        std::thread::sleep(SLIDE_DUR);
+    }
+    Ok(())
+}
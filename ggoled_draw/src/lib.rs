@@ -1,23 +1,36 @@
-// This is a wrapper around `ggoled_lib` that has high-level draw functions and additional events.
-// Heavily specialised for `ggoled_cli` and `ggoled_app`, and is therefore not recommended for general use.
+//! A wrapper around `ggoled_lib` with layer-based drawing, text rendering, animations and device events.
+//!
+//! The stable surface for building custom display apps is [`DrawDevice`], [`DrawLayer`], [`LayerTxn`],
+//! [`TextRenderer`], [`Frame`] and [`bitmap_from_memory`], along with the types used in their signatures.
+//! Items hidden from the docs only exist to serve `ggoled_cli` and `ggoled_app` and may change at any time.
+//! See `examples/` for a clock and an image slideshow built on the stable surface.
 
-use anyhow::bail;
-use ggoled_lib::{bitmap::BitVec, Bitmap, Device, DeviceEvent};
+use anyhow::{bail, Context};
+use ggoled_lib::{
+    bitmap::{BitVec, GrayMode, BAYER_4X4},
+    Bitmap, Device, DeviceEvent, DeviceWatcher,
+};
 use image::{codecs::gif::GifDecoder, AnimationDecoder, ImageFormat, ImageReader};
 use rusttype::{point, Font, Scale};
 use std::{
-    collections::BTreeMap,
-    path::PathBuf,
+    collections::{hash_map::RandomState, BTreeMap},
+    hash::{BuildHasher, Hasher},
+    io::Read,
+    path::{Path, PathBuf},
     sync::{
-        mpsc::{channel, Receiver, Sender},
-        Arc, Mutex, MutexGuard,
+        atomic::{AtomicBool, Ordering},
+        mpsc::{channel, Receiver, SendError, Sender, TryRecvError},
+        Arc, Mutex, MutexGuard, PoisonError,
     },
+    task::Waker,
     time::{Duration, Instant},
 };
+use tracing::info;
 
 enum FontInner {
     Ttf { font: Font<'static>, size: f32 },
-    Bdf { font: Box<bdf2::Font> },
+    // BDF glyphs have a fixed size, so larger text is made by scaling them up by a whole factor
+    Bdf { font: Box<bdf2::Font>, scale: usize },
 }
 
 impl Clone for FontInner {
@@ -27,61 +40,108 @@ impl Clone for FontInner {
                 font: font.clone(),
                 size: *size,
             },
-            FontInner::Bdf { font } => FontInner::Bdf { font: font.clone() },
+            FontInner::Bdf { font, scale } => FontInner::Bdf {
+                font: font.clone(),
+                scale: *scale,
+            },
         }
     }
 }
 
+// Whole factor to scale a BDF font of height `native_h` by to get closest to `size` pixels, at least 1.
+fn bdf_scale(native_h: u32, size: f32) -> usize {
+    (size / native_h.max(1) as f32).round().max(1.0) as usize
+}
+
+/// Stroke weight of rendered text. `Bold` is synthesized by thickening strokes, so it works with any font.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum FontWeight {
+    #[default]
+    Normal,
+    /// Strokes are widened by a pixel to the right, which makes each line a pixel wider.
+    Bold,
+}
+
+// Classic bitmap emboldening: the bitmap OR-ed with a copy of itself shifted a pixel to the right.
+fn embolden(bitmap: &Bitmap) -> Bitmap {
+    if bitmap.w == 0 {
+        return bitmap.clone();
+    }
+    let mut out = Bitmap::new(bitmap.w + 1, bitmap.h, false);
+    out.blit(bitmap, 0, 0, false);
+    out.blit(bitmap, 1, 0, false);
+    out
+}
+
+/// How anti-aliased TTF/OTF glyph edges are turned into pixels. BDF fonts are already 1-bit and unaffected.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum GlyphRendering {
+    /// Pixels more than half covered are set.
+    #[default]
+    Threshold,
+    /// Partly covered pixels are set in a 4x4 ordered dither pattern, which smooths out small text.
+    Dithered,
+}
+
+/// Renders text into `Bitmap`s using either a TTF/OTF or a BDF font.
 #[derive(Clone)]
 pub struct TextRenderer {
     inner: FontInner,
+    weight: FontWeight,
+    glyph_rendering: GlyphRendering,
 }
 impl TextRenderer {
+    fn from_inner(inner: FontInner) -> Self {
+        Self {
+            inner,
+            weight: FontWeight::Normal,
+            glyph_rendering: GlyphRendering::Threshold,
+        }
+    }
+    /// Load a font from disk. `.bdf` files are loaded as bitmap fonts, anything else as TTF/OTF at `size`.
+    /// Bitmap fonts are scaled up by the whole factor closest to `size` divided by their own height.
     pub fn load_from_file(path: &PathBuf, size: f32) -> anyhow::Result<Self> {
         let ext = path.extension().and_then(|e| e.to_str()).unwrap_or("");
         match ext.to_lowercase().as_str() {
             "bdf" => {
                 let font = bdf2::open(path).map_err(|e| anyhow::anyhow!("Failed to load BDF font: {:?}", e))?;
-                Ok(Self {
-                    inner: FontInner::Bdf { font: Box::new(font) },
-                })
+                let scale = bdf_scale(font.bounds().height, size);
+                Ok(Self::from_inner(FontInner::Bdf {
+                    font: Box::new(font),
+                    scale,
+                }))
             }
             _ => {
                 let data = std::fs::read(path)?;
                 let Some(font) = Font::try_from_vec(data) else {
                     bail!("Failed to load font");
                 };
-                Ok(Self {
-                    inner: FontInner::Ttf { font, size },
-                })
+                Ok(Self::from_inner(FontInner::Ttf { font, size }))
             }
         }
     }
     pub fn new_pixel_operator() -> Self {
-        Self {
-            inner: FontInner::Ttf {
-                font: Font::try_from_bytes(include_bytes!("../fonts/PixelOperator.ttf")).unwrap(),
-                size: 16.0,
-            },
-        }
+        Self::from_inner(FontInner::Ttf {
+            font: Font::try_from_bytes(include_bytes!("../fonts/PixelOperator.ttf")).unwrap(),
+            size: 16.0,
+        })
     }
     pub fn new_merged() -> Self {
         let font_data = include_str!("../fonts/merged.bdf");
         let cursor = std::io::Cursor::new(font_data.as_bytes());
-        Self {
-            inner: FontInner::Bdf {
-                font: Box::new(bdf2::read(cursor).unwrap()),
-            },
-        }
+        Self::from_inner(FontInner::Bdf {
+            font: Box::new(bdf2::read(cursor).unwrap()),
+            scale: 1,
+        })
     }
+    #[doc(hidden)]
     pub fn new_siji() -> Self {
         let font_data = include_str!("../fonts/merged.bdf");
         let cursor = std::io::Cursor::new(font_data.as_bytes());
-        Self {
-            inner: FontInner::Bdf {
-                font: Box::new(bdf2::read(cursor).expect("Failed to parse Merged BDF font")),
-            },
-        }
+        Self::from_inner(FontInner::Bdf {
+            font: Box::new(bdf2::read(cursor).expect("Failed to parse Merged BDF font")),
+            scale: 1,
+        })
     }
     pub fn line_height(&self) -> usize {
         match &self.inner {
@@ -90,10 +150,29 @@ impl TextRenderer {
                 let v_metrics = font.v_metrics(scale);
                 (v_metrics.ascent - v_metrics.descent).ceil() as usize
             }
-            FontInner::Bdf { font } => font.bounds().height as usize,
+            FontInner::Bdf { font, scale } => font.bounds().height as usize * scale,
         }
     }
+    pub fn set_weight(&mut self, weight: FontWeight) {
+        self.weight = weight;
+    }
+    pub fn weight(&self) -> FontWeight {
+        self.weight
+    }
+    pub fn set_glyph_rendering(&mut self, rendering: GlyphRendering) {
+        self.glyph_rendering = rendering;
+    }
+    pub fn glyph_rendering(&self) -> GlyphRendering {
+        self.glyph_rendering
+    }
     pub fn measure_line_widths(&self, text: &str) -> Vec<usize> {
+        let widths = self.measure_plain_line_widths(text);
+        match self.weight {
+            FontWeight::Normal => widths,
+            FontWeight::Bold => widths.into_iter().map(|w| if w > 0 { w + 1 } else { 0 }).collect(),
+        }
+    }
+    fn measure_plain_line_widths(&self, text: &str) -> Vec<usize> {
         let clean_text = text.replace('\r', "");
         let text_lines = clean_text.split('\n');
         match &self.inner {
@@ -112,7 +191,7 @@ impl TextRenderer {
                     })
                     .collect()
             }
-            FontInner::Bdf { font } => {
+            FontInner::Bdf { font, scale } => {
                 let bounds = font.bounds();
                 text_lines
                     .map(|text_line| {
@@ -122,13 +201,80 @@ impl TextRenderer {
                                 cursor_x += glyph.device_width().unwrap_or(&(bounds.width, 0)).0 as i32;
                             }
                         }
-                        cursor_x.max(0) as usize
+                        cursor_x.max(0) as usize * scale
                     })
                     .collect()
             }
         }
     }
+    fn has_glyph(&self, ch: char) -> bool {
+        match &self.inner {
+            FontInner::Ttf { font, .. } => font.glyph(ch).id().0 != 0,
+            FontInner::Bdf { font, .. } => font.glyphs().contains_key(&ch),
+        }
+    }
+    /// Shorten a single line of text to at most `max_w` pixels wide by cutting it off with an ellipsis.
+    /// Lines that already fit are returned as is, and an empty string if not even the ellipsis fits.
+    pub fn truncate_with_ellipsis(&self, line: &str, max_w: usize) -> String {
+        let width = |text: &str| self.measure_line_widths(text)[0];
+        if width(line) <= max_w {
+            return line.to_string();
+        }
+        let ellipsis = if self.has_glyph('…') { "…" } else { "..." };
+        let chars: Vec<char> = line.chars().collect();
+        (0..chars.len())
+            .rev()
+            .map(|n| format!("{}{ellipsis}", chars[..n].iter().collect::<String>().trim_end()))
+            .find(|candidate| width(candidate) <= max_w)
+            .unwrap_or_default()
+    }
+    /// Render each line of `text` into its own `Bitmap`.
     pub fn render_lines(&self, text: &str) -> Vec<Bitmap> {
+        let lines = self.render_plain_lines(text);
+        match self.weight {
+            FontWeight::Normal => lines,
+            FontWeight::Bold => lines.iter().map(embolden).collect(),
+        }
+    }
+    /// Render `text` on a single line knocked out of a filled badge with rounded corners.
+    pub fn render_badge(&self, text: &str) -> Bitmap {
+        let line = self.render_lines(&text.replace(['\r', '\n'], " ")).remove(0);
+        let mut badge = Bitmap::new(line.w + BADGE_PADDING_X * 2, line.h + BADGE_PADDING_Y * 2, false);
+        badge.blit(&line, BADGE_PADDING_X as isize, BADGE_PADDING_Y as isize, false);
+        badge.invert();
+        let (right, bottom) = (badge.w as isize - 1, badge.h as isize - 1);
+        for (x, y) in [(0, 0), (right, 0), (0, bottom), (right, bottom)] {
+            badge.fill_rect(x, y, 1, 1, false);
+        }
+        badge
+    }
+    /// Render `text` as animation frames that type it out one character per frame, `delay` apart. Every frame is
+    /// the size of the whole text, so it stays in place. Animations loop, so swap in the finished text once done.
+    pub fn render_typewriter(&self, text: &str, delay: Duration) -> Vec<Frame> {
+        let clean_text = text.replace('\r', "");
+        let lines = self.render_lines(&clean_text);
+        let line_h = self.line_height();
+        let block_w = lines.iter().map(|line| line.w).max().unwrap_or(0);
+        let mut block = Bitmap::new(block_w, line_h * lines.len(), false);
+        let mut frames = vec![];
+        for ((i, line), text_line) in lines.iter().enumerate().zip(clean_text.split('\n')) {
+            let y = (i * line_h) as isize;
+            let chars: Vec<char> = text_line.chars().collect();
+            for n in 1..=chars.len() {
+                let prefix: String = chars[..n].iter().collect();
+                let w = self.measure_line_widths(&prefix)[0];
+                let mut frame = block.clone();
+                frame.blit_region(line, (0, 0, w, line.h), 0, y, false);
+                frames.push(Frame {
+                    bitmap: Arc::new(frame),
+                    delay: Some(delay),
+                });
+            }
+            block.blit(line, 0, y, false);
+        }
+        frames
+    }
+    fn render_plain_lines(&self, text: &str) -> Vec<Bitmap> {
         let clean_text = text.replace('\r', "");
         let text_lines = clean_text.split('\n');
         match &self.inner {
@@ -151,21 +297,41 @@ impl TextRenderer {
                         let line_h = (line_h + line_h_offset) as usize;
                         let mut bitmap = Bitmap::new(line_w, line_h, false);
                         for glyph in glyphs {
-                            if let Some(bb) = glyph.pixel_bounding_box() {
-                                glyph.draw(|x, y, v| {
+                            let Some(bb) = glyph.pixel_bounding_box() else {
+                                continue;
+                            };
+                            let (px, py) = (line_w_offset + bb.min.x, line_h_offset + bb.min.y);
+                            match self.glyph_rendering {
+                                GlyphRendering::Threshold => glyph.draw(|x, y, v| {
                                     if v > 0.5 {
-                                        let px = (x as i32 + line_w_offset + bb.min.x) as usize;
-                                        let py = (y as i32 + line_h_offset + bb.min.y) as usize;
+                                        let px = (x as i32 + px) as usize;
+                                        let py = (y as i32 + py) as usize;
                                         bitmap.data.set(py * line_w + px, true);
                                     }
-                                })
+                                }),
+                                GlyphRendering::Dithered => {
+                                    // Dithered against the line's own pixels so overlapping glyphs share the pattern
+                                    let (glyph_w, glyph_h) = (bb.width() as usize, bb.height() as usize);
+                                    let mut coverage = vec![0u8; glyph_w * glyph_h];
+                                    glyph.draw(|x, y, v| {
+                                        coverage[x as usize + y as usize * glyph_w] = (v * 255.0).round() as u8;
+                                    });
+                                    bitmap.blit_gray(
+                                        &coverage,
+                                        glyph_w,
+                                        glyph_h,
+                                        px as isize,
+                                        py as isize,
+                                        GrayMode::Bayer4,
+                                    );
+                                }
                             }
                         }
                         bitmap
                     })
                     .collect()
             }
-            FontInner::Bdf { font } => {
+            FontInner::Bdf { font, scale } => {
                 let bounds = font.bounds();
                 let line_h = bounds.height as usize;
                 text_lines
@@ -205,36 +371,199 @@ impl TextRenderer {
                         }
                         bitmap
                     })
+                    .map(|bitmap| bitmap.scale(*scale))
                     .collect()
             }
         }
     }
 }
 
+fn pixel_luma(p: &image::Rgba<u8>) -> usize {
+    ((p.0[0] as usize) + (p.0[1] as usize) + (p.0[2] as usize)) / 3
+}
+
+// Returns the brightness of the brighter color if the image has exactly two, like 1-bit or two-color palette art.
+fn two_color_on_luma(img: &image::RgbaImage) -> Option<usize> {
+    let mut lumas = Vec::with_capacity(2);
+    for p in img.pixels() {
+        let luma = pixel_luma(p);
+        if !lumas.contains(&luma) {
+            if lumas.len() == 2 {
+                return None;
+            }
+            lumas.push(luma);
+        }
+    }
+    (lumas.len() == 2).then(|| lumas[0].max(lumas[1]))
+}
+
+// Two-color images map their brighter color to set pixels directly, so art made for the display doesn't depend on
+// the threshold. Everything else is thresholded.
 fn bitmap_from_image(img: &image::RgbaImage, threshold: u8) -> Bitmap {
+    let on_luma = two_color_on_luma(img);
     Bitmap {
         w: img.width() as usize,
         h: img.height() as usize,
         data: img
             .pixels()
-            .map(|p| (((p.0[0] as usize) + (p.0[1] as usize) + (p.0[2] as usize)) / 3) >= threshold as usize)
+            .map(|p| match on_luma {
+                Some(on_luma) => pixel_luma(p) == on_luma,
+                None => pixel_luma(p) >= threshold as usize,
+            })
             .collect::<BitVec>(),
     }
 }
 fn bitmap_from_dynimage(img: &image::DynamicImage, threshold: u8) -> Bitmap {
     bitmap_from_image(&img.to_rgba8(), threshold)
 }
+/// Decode an image and convert it to a `Bitmap`, turning on pixels at or above the grayscale `threshold`.
+/// Images with only two colors, like 1-bit art, turn on the brighter color regardless of `threshold`.
 pub fn bitmap_from_memory(buf: &[u8], threshold: u8) -> anyhow::Result<Bitmap> {
     let img = image::load_from_memory(buf)?;
     Ok(bitmap_from_dynimage(&img, threshold))
 }
+/// Like `bitmap_from_memory`, but converting images that aren't two-color with `mode`, e.g. to dither photos.
+pub fn bitmap_from_memory_with_mode(buf: &[u8], mode: GrayMode) -> anyhow::Result<Bitmap> {
+    let img = image::load_from_memory(buf)?;
+    Ok(bitmap_from_image_with_mode(&img.to_rgba8(), mode))
+}
 
-#[derive(Clone)]
+/// Encode a `Bitmap` as a black and white PNG.
+pub fn bitmap_to_png(bitmap: &Bitmap) -> anyhow::Result<Vec<u8>> {
+    let pixels = bitmap.data.iter().map(|on| if on { 0xff } else { 0x00 }).collect();
+    let Some(img) = image::GrayImage::from_raw(bitmap.w as u32, bitmap.h as u32, pixels) else {
+        bail!("bitmap size doesn't match its data");
+    };
+    let mut buf = std::io::Cursor::new(vec![]);
+    img.write_to(&mut buf, ImageFormat::Png)?;
+    Ok(buf.into_inner())
+}
+
+/// Encode a `Bitmap` as a black and white PNG with each pixel enlarged to a `scale`x`scale` block, which makes a
+/// 128x64 screen easier to see. A `scale` of 0 is treated as 1.
+pub fn bitmap_to_png_scaled(bitmap: &Bitmap, scale: usize) -> anyhow::Result<Vec<u8>> {
+    bitmap_to_png(&bitmap.scale(scale.max(1)))
+}
+
+const ART_FETCH_TIMEOUT: Duration = Duration::from_secs(3);
+const ART_MAX_BYTES: u64 = 8 * 1024 * 1024;
+
+// Decodes `%XX` escapes, as used in `file://` URLs
+fn percent_decode(s: &str) -> anyhow::Result<String> {
+    let bytes = s.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let escaped = (bytes[i] == b'%')
+            .then(|| s.get(i + 1..i + 3))
+            .flatten()
+            .and_then(|hex| u8::from_str_radix(hex, 16).ok());
+        match escaped {
+            Some(byte) => {
+                out.push(byte);
+                i += 3;
+            }
+            None => {
+                out.push(bytes[i]);
+                i += 1;
+            }
+        }
+    }
+    Ok(String::from_utf8(out)?)
+}
+
+fn read_art_url(url: &str) -> anyhow::Result<Vec<u8>> {
+    if url.starts_with("http://") || url.starts_with("https://") {
+        let mut buf = vec![];
+        ureq::get(url)
+            .timeout(ART_FETCH_TIMEOUT)
+            .call()?
+            .into_reader()
+            .take(ART_MAX_BYTES)
+            .read_to_end(&mut buf)?;
+        return Ok(buf);
+    }
+    let path = match url.strip_prefix("file://") {
+        Some(path) => percent_decode(path)?,
+        None if url.contains("://") => bail!("unsupported album art URL: {url}"),
+        None => url.to_string(),
+    };
+    std::fs::read(&path).with_context(|| format!("failed to read {path}"))
+}
+
+// Ordered dithering keeps some of the shading that a plain threshold would lose on photos
+fn gray_bitmap(img: &image::RgbaImage, mode: GrayMode) -> Bitmap {
+    let (w, h) = (img.width() as usize, img.height() as usize);
+    let lumas: Vec<u8> = img.pixels().map(|p| pixel_luma(p) as u8).collect();
+    let mut bitmap = Bitmap::new(w, h, false);
+    bitmap.blit_gray(&lumas, w, h, 0, 0, mode);
+    bitmap
+}
+
+// Like `bitmap_from_image`, with `mode` for images that aren't two-color
+fn bitmap_from_image_with_mode(img: &image::RgbaImage, mode: GrayMode) -> Bitmap {
+    if two_color_on_luma(img).is_some() {
+        // The threshold is ignored for two-color images
+        bitmap_from_image(img, u8::MAX)
+    } else {
+        gray_bitmap(img, mode)
+    }
+}
+
+/// Decode album art and dither it to fit in `size`x`size`.
+#[doc(hidden)]
+pub fn album_art_from_memory(buf: &[u8], size: u32) -> anyhow::Result<Bitmap> {
+    let img = image::load_from_memory(buf)?.thumbnail(size, size);
+    Ok(gray_bitmap(&img.to_rgba8(), GrayMode::Bayer4))
+}
+
+/// Load album art from a `file://` or `http(s)://` URL, or a plain path, and dither it to fit in `size`x`size`.
+#[doc(hidden)]
+pub fn load_album_art(url: &str, size: u32) -> anyhow::Result<Bitmap> {
+    album_art_from_memory(&read_art_url(url)?, size)
+}
+
+/// A single animation frame. A `delay` of `None` means the frame follows the draw rate.
+#[derive(Clone, Debug, PartialEq)]
 pub struct Frame {
     pub bitmap: Arc<Bitmap>,
     pub delay: Option<Duration>,
 }
 
+// GIF delays are rational (e.g. 100/3 ms), so keep the fraction instead of truncating to whole milliseconds
+fn frame_delay(delay: image::Delay) -> Duration {
+    let (numer, denom) = delay.numer_denom_ms();
+    Duration::from_nanos(numer as u64 * 1_000_000 / denom.max(1) as u64)
+}
+
+/// Decode an image into animation frames like `bitmap_from_memory`. GIFs give one frame per GIF frame with its
+/// delay, while other images give a single frame that follows the draw rate.
+pub fn frames_from_memory(buf: &[u8], threshold: u8) -> anyhow::Result<Vec<Frame>> {
+    decode_frames_with(buf, |img| bitmap_from_image(img, threshold))
+}
+/// Like `frames_from_memory`, but converting frames that aren't two-color with `mode`, e.g. to dither photos.
+pub fn frames_from_memory_with_mode(buf: &[u8], mode: GrayMode) -> anyhow::Result<Vec<Frame>> {
+    decode_frames_with(buf, |img| bitmap_from_image_with_mode(img, mode))
+}
+
+fn decode_frames_with(buf: &[u8], to_bitmap: impl Fn(&image::RgbaImage) -> Bitmap) -> anyhow::Result<Vec<Frame>> {
+    if image::guess_format(buf)? != ImageFormat::Gif {
+        let bitmap = Arc::new(to_bitmap(&image::load_from_memory(buf)?.to_rgba8()));
+        return Ok(vec![Frame { bitmap, delay: None }]);
+    }
+    let frames = GifDecoder::new(std::io::Cursor::new(buf))?
+        .into_frames()
+        .collect_frames()?;
+    Ok(frames
+        .iter()
+        .map(|frame| Frame {
+            bitmap: Arc::new(to_bitmap(frame.buffer())),
+            delay: Some(frame_delay(frame.delay())),
+        })
+        .collect())
+}
+
+#[doc(hidden)]
 pub fn decode_frames(path: &str, threshold: u8) -> Vec<Frame> {
     let reader = ImageReader::open(path).expect("Failed to open image");
     if matches!(reader.format().unwrap(), ImageFormat::Gif) {
@@ -246,7 +575,7 @@ pub fn decode_frames(path: &str, threshold: u8) -> Vec<Frame> {
                 let bitmap = Arc::new(bitmap_from_image(frame.buffer(), threshold));
                 Frame {
                     bitmap,
-                    delay: Some(Duration::from_millis(frame.delay().numer_denom_ms().0 as u64)),
+                    delay: Some(frame_delay(frame.delay())),
                 }
             })
             .collect()
@@ -257,7 +586,35 @@ pub fn decode_frames(path: &str, threshold: u8) -> Vec<Frame> {
     }
 }
 
-#[derive(Clone, Copy, PartialEq, PartialOrd, Hash, Eq, Ord)]
+/// Shared handle to the frame a `DrawDevice` most recently composed, usable from any thread.
+#[derive(Clone, Default)]
+pub struct FrameSnapshot(Arc<Mutex<Option<Arc<Bitmap>>>>);
+impl FrameSnapshot {
+    /// The last composed frame, or `None` if nothing has been drawn yet.
+    pub fn latest(&self) -> Option<Arc<Bitmap>> {
+        self.0.lock().unwrap_or_else(PoisonError::into_inner).clone()
+    }
+    fn set(&self, frame: Arc<Bitmap>) {
+        *self.0.lock().unwrap_or_else(PoisonError::into_inner) = Some(frame);
+    }
+}
+
+// Connection state published by the draw thread, so it can be queried without waiting for events.
+struct DeviceStatus {
+    connected: AtomicBool,
+    last_draw_ok: AtomicBool,
+}
+impl DeviceStatus {
+    fn new() -> Self {
+        DeviceStatus {
+            connected: AtomicBool::new(true),
+            last_draw_ok: AtomicBool::new(true),
+        }
+    }
+}
+
+/// Handle to a layer added to a `DrawDevice`. Layers are drawn in the order they were added.
+#[derive(Clone, Copy, Debug, PartialEq, PartialOrd, Hash, Eq, Ord)]
 pub struct LayerId(usize);
 impl LayerId {
     pub fn none() -> LayerId {
@@ -265,6 +622,8 @@ impl LayerId {
     }
 }
 
+/// Content drawn by a `DrawDevice` every frame. Variants without `NoShift` move along with the OLED shift.
+#[derive(Clone, Debug, PartialEq)]
 pub enum DrawLayer {
     Image {
         bitmap: Arc<Bitmap>,
@@ -290,29 +649,132 @@ pub enum DrawLayer {
         bitmap: Arc<Bitmap>,
         y: isize,
     },
+    Series {
+        values: Vec<f32>,
+        x: isize,
+        y: isize,
+        w: usize,
+        h: usize,
+    },
+    Slideshow {
+        images: Vec<Arc<Bitmap>>,
+        interval: Duration,
+        x: isize,
+        y: isize,
+    },
+}
+
+/// How a layer animates from its old bitmap to a new one given to `set_layer_bitmap`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum TransitionKind {
+    /// The new bitmap is revealed from left to right.
+    Wipe,
+    /// The old bitmap slides out to the left while the new one slides in from the right.
+    Slide,
+    /// Pixels switch over in an ordered dither pattern.
+    Dissolve,
+}
+
+/// Opt-in transition for a single layer, see `DrawDevice::set_layer_transition`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct TransitionConfig {
+    pub kind: TransitionKind,
+    /// Length of the transition in rendered frames.
+    pub frames: usize,
+}
+
+/// A layer and its settings as exported by `DrawDevice::export_state`. Playback progress isn't included.
+#[derive(Clone, Debug, PartialEq)]
+pub struct LayerSnapshot {
+    pub id: LayerId,
+    pub layer: DrawLayer,
+    pub transition: Option<TransitionConfig>,
+    pub clip: Option<(usize, usize, usize, usize)>,
+    pub density: f32,
 }
 
+/// What happens to lines of text wider than the screen.
 #[derive(Clone, Copy)]
 pub enum TextOverflowMode {
     Scroll,
+    /// Scroll all lines together as one block if any of them doesn't fit, so they never move out of step.
+    ScrollTogether,
     Clip,
+    /// Cut the line short with an ellipsis so that it fits.
+    Ellipsis,
 }
 
+/// How everything but the `NoShift` layers moves around the screen over time, to spread out OLED burn-in.
+#[derive(Clone, Copy, Debug, PartialEq)]
 pub enum ShiftMode {
     Off,
+    /// Step through 9 positions within a pixel of the origin, one every 90 seconds.
     Simple,
+    /// Like `Simple`, but stepping every `period` and up to `amplitude` pixels away from the origin.
+    Cycle {
+        period: Duration,
+        amplitude: isize,
+    },
+    /// Move to a random offset within `radius` pixels every `period`. The same `seed` gives the same offsets,
+    /// otherwise they differ every time the mode is set.
+    Jitter {
+        period: Duration,
+        radius: isize,
+        seed: Option<u64>,
+    },
+}
+
+/// How the draw thread waits between frames.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum TimingMode {
+    /// Spin for the last part of each wait for accurate frame pacing, at the cost of some CPU.
+    #[default]
+    Precise,
+    /// Plain thread sleeps, which use less power but may overshoot frames by the OS timer resolution.
+    Relaxed,
+}
+
+/// Inverts the whole screen for `duration` at the end of every `interval`, to even out OLED wear from
+/// mostly static content like a clock.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct InversionSchedule {
+    pub interval: Duration,
+    pub duration: Duration,
+}
+impl InversionSchedule {
+    fn is_inverted(&self, elapsed: Duration) -> bool {
+        if self.interval.is_zero() {
+            return false;
+        }
+        elapsed.as_nanos() % self.interval.as_nanos() >= self.interval.saturating_sub(self.duration).as_nanos()
+    }
 }
 
+type FrameHook = Box<dyn FnMut(&mut Bitmap) + Send>;
+
 enum DrawCommand {
     Play,
     Pause,
+    Blank,
+    ForceRedraw,
+    FreezeMotion(bool),
     SetVolume(u8),
+    SetBrightness(u8),
     SetShiftMode(ShiftMode),
+    SetDevicePresent(bool),
+    SetScrollSpeed(f32),
+    SetTimingMode(TimingMode),
+    SetFrameHook(Option<FrameHook>),
+    SetForcedRedrawInterval(Option<Duration>),
+    SetInversionSchedule(Option<InversionSchedule>),
+    LogFrame,
     Stop,
 }
 
 #[derive(Debug)]
 pub enum DrawEvent {
+    /// The error that caused the following `DeviceDisconnected`, for diagnostics.
+    DeviceError(String),
     DeviceDisconnected,
     DeviceReconnected,
     DeviceEvent(DeviceEvent),
@@ -323,14 +785,25 @@ struct AnimState {
     next_update: Instant,
 }
 struct ScrollState {
-    x: isize,
+    x: f32,
     pause_until: Option<Instant>,
 }
 
+struct TransitionState {
+    config: Option<TransitionConfig>,
+    from: Option<Arc<Bitmap>>,
+    // Position of `from` relative to the layer's current position, when the layer moved as well
+    from_offset: (isize, isize),
+    step: usize,
+}
+
 struct DrawLayerState {
     layer: DrawLayer,
     anim: AnimState,
     scroll: ScrollState,
+    transition: TransitionState,
+    clip: Option<(usize, usize, usize, usize)>,
+    density: f32,
 }
 
 enum RenderOp {
@@ -339,12 +812,17 @@ enum RenderOp {
         x: isize,
         y: isize,
     },
-    Scroll {
+    BlitRegion {
         bitmap: Arc<Bitmap>,
+        region: (usize, usize, usize, usize),
         x: isize,
         y: isize,
+    },
+    Scroll {
+        bitmap: Arc<Bitmap>,
+        x: f32,
+        y: isize,
         scroll_w: isize,
-        dupes: usize,
     },
 }
 
@@ -361,55 +839,190 @@ const OLED_SHIFTS: [(isize, isize); 9] = [
     (-1, -1),
 ];
 
+// Picks the next `ShiftMode::Jitter` offset within `radius`, different from `prev` where possible. `state` is a
+// splitmix64 generator, so a seed always gives the same offsets.
+fn next_jitter_shift(state: &mut u64, radius: isize, prev: (isize, isize)) -> (isize, isize) {
+    let radius = radius.max(0);
+    if radius == 0 {
+        return (0, 0);
+    }
+    let mut next = || {
+        *state = state.wrapping_add(0x9e3779b97f4a7c15);
+        let mut z = *state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d049bb133111eb);
+        let z = z ^ (z >> 31);
+        (z % (radius as u64 * 2 + 1)) as isize - radius
+    };
+    loop {
+        let shift = (next(), next());
+        if shift != prev {
+            return shift;
+        }
+    }
+}
+
+// Offset of step `index` in the shift cycle, with the unit steps scaled up to `amplitude` pixels
+fn cycle_shift(index: usize, amplitude: isize) -> (isize, isize) {
+    let (x, y) = OLED_SHIFTS[index % OLED_SHIFTS.len()];
+    (x * amplitude, y * amplitude)
+}
+
+// Set to log every changed frame, for bug reports about what ends up on the screen
+const LOG_FRAMES_ENV: &str = "GGOLED_LOG_FRAMES";
+const FORCED_REDRAW_INTERVAL: Duration = Duration::from_secs(1);
 const RECONNECT_PERIOD: Duration = Duration::from_secs(1);
+const MAX_RECONNECT_PERIOD: Duration = Duration::from_secs(30);
+const RECONNECT_JITTER: f64 = 0.2;
+const DEVICE_WATCH_PERIOD: Duration = Duration::from_millis(500);
 const SCROLL_REVOLUTION_PAUSE: Duration = Duration::from_millis(900);
 const MAX_ANIM_CATCHUP_STEPS: usize = 8;
+const MAX_FRAME_CATCHUP: u32 = 8;
+const BADGE_PADDING_X: usize = 2;
+const BADGE_PADDING_Y: usize = 1;
+
+// Layer maps are only changed through the helpers in this file, which leave them valid even if they panic halfway
+// (e.g. a font rendering bug), so a poisoned lock is recovered rather than taking down every later draw.
+fn lock_layers(layers: &Mutex<LayerMap>) -> MutexGuard<'_, LayerMap> {
+    layers.lock().unwrap_or_else(PoisonError::into_inner)
+}
+
+fn new_layer_state(layer: DrawLayer) -> DrawLayerState {
+    DrawLayerState {
+        layer,
+        anim: AnimState {
+            ticks: 0,
+            next_update: Instant::now(),
+        },
+        scroll: ScrollState {
+            x: 0.0,
+            pause_until: None,
+        },
+        transition: TransitionState {
+            config: None,
+            from: None,
+            from_offset: (0, 0),
+            step: 0,
+        },
+        clip: None,
+        density: 1.0,
+    }
+}
 
 fn add_layer_to_map(layer_counter: &mut usize, layers: &mut LayerMap, layer: DrawLayer) -> LayerId {
     *layer_counter += 1;
     let id = LayerId(*layer_counter);
-    _ = layers.insert(
-        id,
-        DrawLayerState {
-            layer,
-            anim: AnimState {
-                ticks: 0,
-                next_update: Instant::now(),
-            },
-            scroll: ScrollState {
-                x: 0,
-                pause_until: None,
-            },
-        },
-    );
+    _ = layers.insert(id, new_layer_state(layer));
     id
 }
 
+fn try_add_layer_to_map(
+    layer_counter: &mut usize,
+    layers: &mut LayerMap,
+    layer_limit: Option<usize>,
+    layer: DrawLayer,
+) -> Option<LayerId> {
+    if layer_limit_reached(layers, layer_limit) {
+        return None;
+    }
+    Some(add_layer_to_map(layer_counter, layers, layer))
+}
+
+fn layer_limit_reached(layers: &LayerMap, layer_limit: Option<usize>) -> bool {
+    layer_limit.is_some_and(|limit| layers.len() >= limit)
+}
+
+fn export_layers(layers: &LayerMap) -> Vec<LayerSnapshot> {
+    layers
+        .iter()
+        .map(|(id, state)| LayerSnapshot {
+            id: *id,
+            layer: state.layer.clone(),
+            transition: state.transition.config,
+            clip: state.clip,
+            density: state.density,
+        })
+        .collect()
+}
+
+// Replaces all layers with the snapshot, keeping their ids so that handles from before the export still work.
+// Layers past the layer limit are left out.
+fn restore_layers(
+    layer_counter: &mut usize,
+    layers: &mut LayerMap,
+    layer_limit: Option<usize>,
+    snapshot: Vec<LayerSnapshot>,
+) {
+    layers.clear();
+    for layer in snapshot {
+        if layer_limit_reached(layers, layer_limit) {
+            break;
+        }
+        *layer_counter = (*layer_counter).max(layer.id.0);
+        let mut state = new_layer_state(layer.layer);
+        state.transition.config = layer.transition;
+        state.clip = layer.clip;
+        state.density = layer.density;
+        layers.insert(layer.id, state);
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
 fn add_text_layers(
     texter: &TextRenderer,
     width: usize,
     height: usize,
     layer_counter: &mut usize,
     layers: &mut LayerMap,
+    layer_limit: Option<usize>,
     text: &str,
     x: Option<isize>,
     y: Option<isize>,
     shift: bool,
     mode: TextOverflowMode,
 ) -> Vec<LayerId> {
-    let bitmaps: Vec<_> = texter.render_lines(text).into_iter().map(Arc::new).collect();
+    let truncated;
+    let text = if matches!(mode, TextOverflowMode::Ellipsis) {
+        let max_w = width.saturating_sub(x.unwrap_or(0).max(0) as usize);
+        truncated = text
+            .replace('\r', "")
+            .split('\n')
+            .map(|line| texter.truncate_with_ellipsis(line, max_w))
+            .collect::<Vec<_>>()
+            .join("\n");
+        &truncated
+    } else {
+        text
+    };
+    let mut bitmaps: Vec<_> = texter.render_lines(text).into_iter().map(Arc::new).collect();
+    let scroll_together =
+        matches!(mode, TextOverflowMode::ScrollTogether) && bitmaps.iter().any(|bitmap| bitmap.w >= width);
+    if scroll_together {
+        // Lines padded to the same width scroll at the same rate and wrap around at the same time
+        let block_w = bitmaps.iter().map(|bitmap| bitmap.w).max().unwrap_or(0);
+        for bitmap in &mut bitmaps {
+            if bitmap.w < block_w {
+                let mut padded = Bitmap::new(block_w, bitmap.h, false);
+                padded.blit(bitmap, 0, 0, false);
+                *bitmap = Arc::new(padded);
+            }
+        }
+    }
     let line_height = texter.line_height();
     let center_y: isize = (height as isize - (line_height * bitmaps.len()) as isize) / 2;
+    // Lines stop being added once the layer limit is reached
     bitmaps
         .into_iter()
         .enumerate()
-        .map(|(i, bitmap)| {
+        .map_while(|(i, bitmap)| {
             let y = y.unwrap_or(center_y) + (i * line_height) as isize;
-            let center_x = (width as isize - bitmap.w as isize) / 2;
-            if bitmap.w >= width && matches!(mode, TextOverflowMode::Scroll) {
-                add_layer_to_map(
+            // Lines that don't fit show their start rather than their middle
+            let center_x = ((width as isize - bitmap.w as isize) / 2).max(0);
+            if scroll_together || (bitmap.w >= width && matches!(mode, TextOverflowMode::Scroll)) {
+                try_add_layer_to_map(
                     layer_counter,
                     layers,
+                    layer_limit,
                     if shift {
                         DrawLayer::Scroll { bitmap, y }
                     } else {
@@ -417,9 +1030,10 @@ fn add_text_layers(
                     },
                 )
             } else {
-                add_layer_to_map(
+                try_add_layer_to_map(
                     layer_counter,
                     layers,
+                    layer_limit,
                     if shift {
                         DrawLayer::Image {
                             bitmap,
@@ -439,6 +1053,282 @@ fn add_text_layers(
         .collect()
 }
 
+// Loads an image file as a layer centered on a `width`x`height` screen, as an animation for GIFs
+fn image_file_layer(path: &Path, threshold: u8, width: usize, height: usize) -> anyhow::Result<DrawLayer> {
+    let buf = std::fs::read(path).with_context(|| format!("failed to read {}", path.display()))?;
+    let mut frames = frames_from_memory(&buf, threshold)?;
+    let Some(first) = frames.first() else {
+        bail!("{} has no frames", path.display());
+    };
+    let x = (width as isize - first.bitmap.w as isize) / 2;
+    let y = (height as isize - first.bitmap.h as isize) / 2;
+    Ok(if frames.len() == 1 {
+        DrawLayer::Image {
+            bitmap: frames.remove(0).bitmap,
+            x,
+            y,
+        }
+    } else {
+        DrawLayer::Animation {
+            frames,
+            x,
+            y,
+            follow_fps: false,
+        }
+    })
+}
+
+// Appends a sample to a `DrawLayer::Series`, keeping only as many values as fit in its width.
+fn push_series_sample(layers: &mut LayerMap, id: LayerId, value: f32) {
+    if let Some(DrawLayerState {
+        layer: DrawLayer::Series { values, w, .. },
+        ..
+    }) = layers.get_mut(&id)
+    {
+        values.push(value);
+        if values.len() > *w {
+            values.drain(..values.len() - *w);
+        }
+    }
+}
+
+fn set_layer_transition(layers: &mut LayerMap, id: LayerId, config: Option<TransitionConfig>) {
+    if let Some(state) = layers.get_mut(&id) {
+        state.transition.config = config;
+        if config.is_none() {
+            state.transition.from = None;
+        }
+    }
+}
+
+fn set_layer_clip(layers: &mut LayerMap, id: LayerId, clip: Option<(usize, usize, usize, usize)>) {
+    if let Some(state) = layers.get_mut(&id) {
+        state.clip = clip;
+    }
+}
+
+fn set_layer_density(layers: &mut LayerMap, id: LayerId, density: f32) {
+    if let Some(state) = layers.get_mut(&id) {
+        state.density = density.clamp(0.0, 1.0);
+    }
+}
+
+// Thins out a bitmap with an ordered dither so that only about `density` of its lit pixels stay on.
+fn apply_density(bitmap: Arc<Bitmap>, density: f32) -> Arc<Bitmap> {
+    if density >= 1.0 {
+        return bitmap;
+    }
+    let mut out = (*bitmap).clone();
+    for y in 0..out.h {
+        for x in 0..out.w {
+            if BAYER_4X4[y % 4][x % 4] as f32 >= density * 16.0 {
+                out.data.set(x + y * out.w, false);
+            }
+        }
+    }
+    Arc::new(out)
+}
+
+// Blits an image layer's bitmap, limited to its clip region if it has one.
+fn image_render_op(bitmap: Arc<Bitmap>, clip: Option<(usize, usize, usize, usize)>, x: isize, y: isize) -> RenderOp {
+    match clip {
+        Some(region) => RenderOp::BlitRegion { bitmap, region, x, y },
+        None => RenderOp::Blit { bitmap, x, y },
+    }
+}
+
+// Replaces the bitmap of an image or scroll layer, starting a transition if the layer has one configured.
+fn set_layer_bitmap(layers: &mut LayerMap, id: LayerId, new_bitmap: Arc<Bitmap>) {
+    let Some(state) = layers.get_mut(&id) else {
+        return;
+    };
+    match &mut state.layer {
+        DrawLayer::Image { bitmap, .. } | DrawLayer::ImageNoShift { bitmap, .. } => {
+            if state.transition.config.is_some() && **bitmap != *new_bitmap {
+                state.transition.from = Some(std::mem::replace(bitmap, new_bitmap));
+                state.transition.from_offset = (0, 0);
+                state.transition.step = 0;
+            } else {
+                *bitmap = new_bitmap;
+            }
+        }
+        DrawLayer::Scroll { bitmap, .. } | DrawLayer::ScrollNoShift { bitmap, .. } => *bitmap = new_bitmap,
+        _ => {}
+    }
+}
+
+// Moves the contents of the image layer `new` into the image layer `old` if `old` has a transition, so it
+// transitions to the new bitmap and position. Returns whether it did, in which case `new` is removed.
+fn morph_layer(layers: &mut LayerMap, old: LayerId, new: LayerId) -> bool {
+    let Some(new_state) = layers.get(&new) else {
+        return false;
+    };
+    let (new_bitmap, new_x, new_y, new_shift) = match &new_state.layer {
+        DrawLayer::Image { bitmap, x, y } => (bitmap.clone(), *x, *y, true),
+        DrawLayer::ImageNoShift { bitmap, x, y } => (bitmap.clone(), *x, *y, false),
+        _ => return false,
+    };
+    let Some(state) = layers.get_mut(&old).filter(|state| state.transition.config.is_some()) else {
+        return false;
+    };
+    let (bitmap, x, y) = match &mut state.layer {
+        DrawLayer::Image { bitmap, x, y } if new_shift => (bitmap, x, y),
+        DrawLayer::ImageNoShift { bitmap, x, y } if !new_shift => (bitmap, x, y),
+        _ => return false,
+    };
+    if **bitmap != *new_bitmap || (*x, *y) != (new_x, new_y) {
+        state.transition.from = Some(std::mem::replace(bitmap, new_bitmap));
+        state.transition.from_offset = (*x - new_x, *y - new_y);
+        state.transition.step = 0;
+        (*x, *y) = (new_x, new_y);
+    }
+    layers.remove(&new);
+    true
+}
+
+fn morph_layers(layers: &mut LayerMap, old: &[LayerId], new: Vec<LayerId>) -> Vec<LayerId> {
+    let mut remaining = Vec::with_capacity(new.len());
+    for (i, new_id) in new.into_iter().enumerate() {
+        match old.get(i) {
+            Some(&old_id) if morph_layer(layers, old_id, new_id) => remaining.push(old_id),
+            Some(&old_id) => {
+                layers.remove(&old_id);
+                remaining.push(new_id);
+            }
+            None => remaining.push(new_id),
+        }
+    }
+    for id in old.iter().skip(remaining.len()) {
+        layers.remove(id);
+    }
+    remaining
+}
+
+// Positions of the repeated copies of a scrolling bitmap that together cover a screen `width` wide. `x` is
+// normally in `(-scroll_w, 0]`, but the OLED shift can push it right of the edge, which then needs the copy before.
+fn scroll_copy_positions(x: f32, scroll_w: isize, width: usize) -> impl Iterator<Item = f32> {
+    let scroll_w = scroll_w.max(1) as f32;
+    let first = x - (x / scroll_w).ceil() * scroll_w;
+    (0..)
+        .map(move |i| first + i as f32 * scroll_w)
+        .take_while(move |copy_x| *copy_x < width as f32)
+}
+
+// Composes frame `step` out of `steps` of a transition. The result covers the area of both bitmaps.
+fn render_transition(from: &Bitmap, to: &Bitmap, kind: TransitionKind, step: usize, steps: usize) -> Bitmap {
+    let w = from.w.max(to.w);
+    let h = from.h.max(to.h);
+    let pixel = |bitmap: &Bitmap, x: usize, y: usize| {
+        x < bitmap.w && y < bitmap.h && bitmap.data.get(x + y * bitmap.w).unwrap_or(false)
+    };
+    let mut out = Bitmap::new(w, h, false);
+    for y in 0..h {
+        for x in 0..w {
+            let on = match kind {
+                TransitionKind::Wipe => {
+                    if x < w * step / steps {
+                        pixel(to, x, y)
+                    } else {
+                        pixel(from, x, y)
+                    }
+                }
+                TransitionKind::Slide => {
+                    let offset = w * step / steps;
+                    if x + offset < w {
+                        pixel(from, x + offset, y)
+                    } else {
+                        pixel(to, x + offset - w, y)
+                    }
+                }
+                TransitionKind::Dissolve => {
+                    if BAYER_4X4[y % 4][x % 4] * steps < 16 * step {
+                        pixel(to, x, y)
+                    } else {
+                        pixel(from, x, y)
+                    }
+                }
+            };
+            out.data.set(x + y * w, on);
+        }
+    }
+    out
+}
+
+// Blits a bitmap at a fractional x position. Pixels that only partially cover a screen column, i.e. the
+// leading and trailing edges, are dithered in proportion to their coverage.
+fn blit_subpixel(screen: &mut Bitmap, bitmap: &Bitmap, x: f32, y: isize) {
+    let base = x.floor();
+    let frac = x - base;
+    if frac == 0.0 {
+        screen.blit(bitmap, base as isize, y, false);
+        return;
+    }
+    let pixel = |x: usize, y: usize| x < bitmap.w && bitmap.data.get(x + y * bitmap.w).unwrap_or(false);
+    let w = bitmap.w + 1;
+    let mut out = Bitmap::new(w, bitmap.h, false);
+    for oy in 0..bitmap.h {
+        for ox in 0..w {
+            let level = BAYER_4X4[oy % 4][ox % 4] as f32 / 16.0;
+            let left = pixel(ox, oy);
+            let right = ox > 0 && pixel(ox - 1, oy);
+            let on = match (left, right) {
+                (true, true) => true,
+                (true, false) => level < 1.0 - frac,
+                (false, true) => level < frac,
+                (false, false) => false,
+            };
+            out.data.set(ox + oy * w, on);
+        }
+    }
+    screen.blit(&out, base as isize, y, false);
+}
+
+// Returns the bitmap to show for a layer this frame and steps its transition forward, if one is running.
+fn advance_transition(transition: &mut TransitionState, bitmap: &Arc<Bitmap>) -> Arc<Bitmap> {
+    let (Some(config), Some(from)) = (transition.config, transition.from.clone()) else {
+        return bitmap.clone();
+    };
+    transition.step += 1;
+    if transition.step >= config.frames {
+        transition.from = None;
+        return bitmap.clone();
+    }
+    let (dx, dy) = transition.from_offset;
+    if (dx, dy) == (0, 0) {
+        return Arc::new(render_transition(
+            &from,
+            bitmap,
+            config.kind,
+            transition.step,
+            config.frames,
+        ));
+    }
+    // Place both bitmaps in the area that covers them, which starts at `transition_origin`
+    let (ox, oy) = (dx.min(0), dy.min(0));
+    let w = ((dx + from.w as isize).max(bitmap.w as isize) - ox) as usize;
+    let h = ((dy + from.h as isize).max(bitmap.h as isize) - oy) as usize;
+    let mut from_area = Bitmap::new(w, h, false);
+    from_area.blit(&from, dx - ox, dy - oy, true);
+    let mut to_area = Bitmap::new(w, h, false);
+    to_area.blit(bitmap, -ox, -oy, true);
+    Arc::new(render_transition(
+        &from_area,
+        &to_area,
+        config.kind,
+        transition.step,
+        config.frames,
+    ))
+}
+
+// Where the bitmap from `advance_transition` goes relative to the layer position, which moves up or left while
+// a moved layer transitions from a position further up or left.
+fn transition_origin(transition: &TransitionState) -> (isize, isize) {
+    match transition.from {
+        Some(_) => (transition.from_offset.0.min(0), transition.from_offset.1.min(0)),
+        None => (0, 0),
+    }
+}
+
 fn normalize_anim_delay(delay: Option<Duration>, fallback_delay: Duration) -> Duration {
     let delay = delay.unwrap_or(fallback_delay);
     if delay.is_zero() {
@@ -480,46 +1370,204 @@ fn advance_animation_state(
     Some(render_idx)
 }
 
-fn run_draw_device_thread(
-    mut dev: Device,
-    layers: Arc<Mutex<LayerMap>>,
+// Unlike animations, slideshows don't catch up on missed slides and always show each image for a full interval.
+fn advance_slideshow_state(anim: &mut AnimState, len: usize, interval: Duration, now: Instant) -> Option<usize> {
+    if len == 0 {
+        return None;
+    }
+    if now >= anim.next_update {
+        anim.ticks = anim.ticks.wrapping_add(1);
+        anim.next_update = now + interval;
+    }
+    Some(anim.ticks.wrapping_sub(1) % len)
+}
+
+// Delay after `failures` failed reconnect attempts, doubling from `RECONNECT_PERIOD` up to `MAX_RECONNECT_PERIOD`.
+// `jitter` (0..1) lengthens it by up to `RECONNECT_JITTER` so that retries don't line up with anything periodic.
+fn reconnect_delay(failures: u32, jitter: f64) -> Duration {
+    let delay = RECONNECT_PERIOD
+        .saturating_mul(1 << failures.min(16))
+        .min(MAX_RECONNECT_PERIOD);
+    delay.mul_f64(1.0 + RECONNECT_JITTER * jitter.clamp(0.0, 1.0))
+}
+
+// Runs `reconnect` and returns whether it worked. The screen may have been reset while disconnected, so on success
+// `prev_screen` is cleared and the current frame is drawn right away even if it hasn't changed.
+fn reconnect_for_redraw(reconnect: impl FnOnce() -> anyhow::Result<()>, prev_screen: &mut Bitmap) -> bool {
+    let reconnected = reconnect().is_ok();
+    if reconnected {
+        *prev_screen = Bitmap::new(0, 0, false);
+    }
+    reconnected
+}
+
+// Returns when the frame after the one due at `target` is due. Frames following a slow one are due right away so the
+// average frame rate holds, but after falling more than `MAX_FRAME_CATCHUP` frames behind the schedule restarts at `now`.
+fn next_frame_target(target: Instant, frame_delay: Duration, now: Instant) -> Instant {
+    let next = target + frame_delay;
+    if now > next + frame_delay * MAX_FRAME_CATCHUP {
+        now
+    } else {
+        next
+    }
+}
+
+fn random_jitter() -> f64 {
+    RandomState::new().build_hasher().finish() as f64 / u64::MAX as f64
+}
+
+type EventWaker = Arc<Mutex<Option<Waker>>>;
+
+// Sends events to the `DrawDevice`, waking up whoever is awaiting the next one.
+struct EventSender {
+    sender: Sender<DrawEvent>,
+    waker: EventWaker,
+}
+impl EventSender {
+    fn send(&self, event: DrawEvent) -> Result<(), SendError<DrawEvent>> {
+        self.sender.send(event)?;
+        self.wake();
+        Ok(())
+    }
+    fn wake(&self) {
+        if let Some(waker) = self.waker.lock().unwrap_or_else(PoisonError::into_inner).take() {
+            waker.wake();
+        }
+    }
+}
+impl Drop for EventSender {
+    // Also wake up once the thread stops, so that waiting for events sees the channel disconnect
+    fn drop(&mut self) {
+        self.wake();
+    }
+}
+
+// Reports a lost device, preceded by the error that revealed it unless it was noticed by the device watcher.
+fn send_device_lost(event_sender: &EventSender, err: Option<anyhow::Error>) -> Result<(), SendError<DrawEvent>> {
+    if let Some(err) = err {
+        event_sender.send(DrawEvent::DeviceError(format!("{err:#}")))?;
+    }
+    event_sender.send(DrawEvent::DeviceDisconnected)
+}
+
+fn run_draw_device_thread(
+    mut dev: Device,
+    layers: Arc<Mutex<LayerMap>>,
     cmd_receiver: Receiver<DrawCommand>,
-    event_sender: Sender<DrawEvent>,
+    event_sender: EventSender,
+    snapshot: FrameSnapshot,
+    status: Arc<DeviceStatus>,
     fps: usize,
 ) -> Device {
     let frame_delay = Duration::from_nanos(1_000_000_000 / fps as u64);
     let mut prev_screen = Bitmap::new(0, 0, false);
     let mut playing = false;
+    let mut blanked = false;
     let mut oled_shift = 0;
     let mut last_shift = Instant::now();
     let mut shift_mode = ShiftMode::Off;
+    let mut jitter_state = 0;
+    let mut jitter_shift = (0, 0);
+    let mut scroll_speed = 1.0;
+    let mut timing_mode = TimingMode::default();
+    let mut frame_hook: Option<FrameHook> = None;
+    let mut forced_redraw_interval = Some(FORCED_REDRAW_INTERVAL);
     let mut connected = true;
-    let mut last_connect_attempt = Instant::now();
+    let mut next_connect_attempt = Instant::now();
+    let mut reconnect_failures = 0;
+    let mut reconnect_now = false;
     let mut last_frame_time = Instant::now();
+    let mut next_frame = Instant::now();
+    let mut frozen_at: Option<Instant> = None;
+    let log_frames = std::env::var_os(LOG_FRAMES_ENV).is_some();
+    let mut log_next_frame = false;
+    let mut inversion: Option<(InversionSchedule, Instant)> = None;
     loop {
         let time = Instant::now();
+        // The thread also stops once the `DrawDevice` is gone, i.e. when either of its channels is disconnected
         let mut stop_after_frame = false;
-        while let Ok(cmd) = cmd_receiver.try_recv() {
+        loop {
+            let cmd = match cmd_receiver.try_recv() {
+                Ok(cmd) => cmd,
+                Err(TryRecvError::Empty) => break,
+                Err(TryRecvError::Disconnected) => {
+                    stop_after_frame = true;
+                    break;
+                }
+            };
             match cmd {
-                DrawCommand::Play => playing = true,
+                DrawCommand::Play => {
+                    playing = true;
+                    blanked = false;
+                }
                 DrawCommand::Pause => playing = false,
+                DrawCommand::Blank => {
+                    playing = false;
+                    blanked = true;
+                }
+                // Whatever is composed next differs from an empty bitmap, so it's sent without waiting for a change
+                DrawCommand::ForceRedraw => prev_screen = Bitmap::new(0, 0, false),
+                DrawCommand::FreezeMotion(freeze) => match (freeze, frozen_at) {
+                    (true, None) => frozen_at = Some(time),
+                    (false, Some(at)) => {
+                        // Resume timers where they left off rather than catching up on the frozen time
+                        let frozen_for = time.duration_since(at);
+                        for state in lock_layers(&layers).values_mut() {
+                            state.anim.next_update += frozen_for;
+                            state.scroll.pause_until = state.scroll.pause_until.map(|until| until + frozen_for);
+                        }
+                        frozen_at = None;
+                    }
+                    _ => {}
+                },
+                // Settings are passed on even while disconnected so the device re-applies them when reconnecting
                 DrawCommand::SetVolume(volume) => {
-                    if connected && dev.set_volume(volume).is_err() {
+                    if let Some(err) = dev.set_volume(volume).err().filter(|_| connected) {
+                        connected = false;
+                        stop_after_frame |= send_device_lost(&event_sender, Some(err)).is_err();
+                    }
+                }
+                DrawCommand::SetBrightness(brightness) => {
+                    if let Some(err) = dev.set_brightness_clamped(brightness).err().filter(|_| connected) {
+                        connected = false;
+                        stop_after_frame |= send_device_lost(&event_sender, Some(err)).is_err();
+                    }
+                }
+                DrawCommand::SetShiftMode(mode) => {
+                    if let ShiftMode::Jitter { seed, .. } = mode {
+                        jitter_state = seed.unwrap_or_else(|| RandomState::new().build_hasher().finish());
+                        jitter_shift = (0, 0);
+                    }
+                    shift_mode = mode;
+                }
+                DrawCommand::SetDevicePresent(present) => {
+                    if !present && connected {
                         connected = false;
-                        event_sender.send(DrawEvent::DeviceDisconnected).unwrap();
+                        stop_after_frame |= send_device_lost(&event_sender, None).is_err();
+                    } else if present && !connected {
+                        reconnect_now = true;
                     }
                 }
-                DrawCommand::SetShiftMode(mode) => shift_mode = mode,
+                DrawCommand::SetScrollSpeed(speed) => scroll_speed = speed,
+                DrawCommand::SetTimingMode(mode) => timing_mode = mode,
+                DrawCommand::SetFrameHook(hook) => frame_hook = hook,
+                DrawCommand::SetForcedRedrawInterval(interval) => forced_redraw_interval = interval,
+                DrawCommand::SetInversionSchedule(schedule) => inversion = schedule.map(|schedule| (schedule, time)),
+                DrawCommand::LogFrame => log_next_frame = true,
                 DrawCommand::Stop => stop_after_frame = true,
             }
         }
 
         // Attempt to reconnect
-        if !connected && time.duration_since(last_connect_attempt) >= RECONNECT_PERIOD {
-            last_connect_attempt = time;
-            if dev.reconnect().is_ok() {
+        if !connected && (reconnect_now || time >= next_connect_attempt) {
+            reconnect_now = false;
+            if reconnect_for_redraw(|| dev.reconnect(), &mut prev_screen) {
                 connected = true;
-                event_sender.send(DrawEvent::DeviceReconnected).unwrap();
+                reconnect_failures = 0;
+                stop_after_frame |= event_sender.send(DrawEvent::DeviceReconnected).is_err();
+            } else {
+                next_connect_attempt = time + reconnect_delay(reconnect_failures, random_jitter());
+                reconnect_failures = reconnect_failures.saturating_add(1);
             }
         }
 
@@ -528,40 +1576,66 @@ fn run_draw_device_thread(
             // Handle OLED shifts
             let (shift_x, shift_y) = match shift_mode {
                 ShiftMode::Off => (0, 0),
-                ShiftMode::Simple => {
-                    if time.duration_since(last_shift) >= OLED_SHIFT_PERIOD {
+                ShiftMode::Simple | ShiftMode::Cycle { .. } => {
+                    let (period, amplitude) = match shift_mode {
+                        ShiftMode::Cycle { period, amplitude } => (period, amplitude),
+                        _ => (OLED_SHIFT_PERIOD, 1),
+                    };
+                    if time.duration_since(last_shift) >= period {
                         oled_shift = (oled_shift + 1) % OLED_SHIFTS.len();
                         last_shift = time;
                     }
-                    OLED_SHIFTS[oled_shift]
+                    cycle_shift(oled_shift, amplitude)
+                }
+                ShiftMode::Jitter { period, radius, .. } => {
+                    if time.duration_since(last_shift) >= period {
+                        jitter_shift = next_jitter_shift(&mut jitter_state, radius, jitter_shift);
+                        last_shift = time;
+                    }
+                    jitter_shift
                 }
             };
 
+            let frozen = frozen_at.is_some();
             let mut render_ops = vec![];
             {
-                let mut layers = layers.lock().unwrap();
+                // Layers stay locked until every render op is collected, so a `LayerTxn` is never split over frames
+                let mut layers = lock_layers(&layers);
                 render_ops.reserve(layers.len());
                 for state in layers.values_mut() {
                     match &state.layer {
-                        DrawLayer::Image { bitmap, x, y } => render_ops.push(RenderOp::Blit {
-                            bitmap: bitmap.clone(),
-                            x: x + shift_x,
-                            y: y + shift_y,
-                        }),
-                        DrawLayer::ImageNoShift { bitmap, x, y } => render_ops.push(RenderOp::Blit {
-                            bitmap: bitmap.clone(),
-                            x: *x,
-                            y: *y,
-                        }),
+                        DrawLayer::Image { bitmap, x, y } => {
+                            let bitmap = advance_transition(&mut state.transition, bitmap);
+                            let (ox, oy) = transition_origin(&state.transition);
+                            render_ops.push(image_render_op(
+                                apply_density(bitmap, state.density),
+                                state.clip,
+                                x + ox + shift_x,
+                                y + oy + shift_y,
+                            ))
+                        }
+                        DrawLayer::ImageNoShift { bitmap, x, y } => {
+                            let bitmap = advance_transition(&mut state.transition, bitmap);
+                            let (ox, oy) = transition_origin(&state.transition);
+                            render_ops.push(image_render_op(
+                                apply_density(bitmap, state.density),
+                                state.clip,
+                                x + ox,
+                                y + oy,
+                            ))
+                        }
                         DrawLayer::Animation {
                             frames,
                             x,
                             y,
                             follow_fps,
                         } => {
-                            if let Some(frame_idx) =
+                            let frame_idx = if frozen {
+                                (!frames.is_empty()).then(|| state.anim.ticks % frames.len())
+                            } else {
                                 advance_animation_state(&mut state.anim, frames, *follow_fps, time, frame_delay)
-                            {
+                            };
+                            if let Some(frame_idx) = frame_idx {
                                 render_ops.push(RenderOp::Blit {
                                     bitmap: frames[frame_idx].bitmap.clone(),
                                     x: x + shift_x,
@@ -569,50 +1643,92 @@ fn run_draw_device_thread(
                                 });
                             }
                         }
+                        // Text that fits, e.g. after being replaced through `set_layer_bitmap`, is centered instead
+                        DrawLayer::Scroll { bitmap, y } if bitmap.w < dev.width => {
+                            state.scroll = ScrollState {
+                                x: 0.0,
+                                pause_until: None,
+                            };
+                            render_ops.push(RenderOp::Blit {
+                                bitmap: apply_density(bitmap.clone(), state.density),
+                                x: (dev.width as isize - bitmap.w as isize) / 2 + shift_x,
+                                y: y + shift_y,
+                            });
+                        }
+                        DrawLayer::ScrollNoShift { bitmap, y } if bitmap.w < dev.width => {
+                            state.scroll = ScrollState {
+                                x: 0.0,
+                                pause_until: None,
+                            };
+                            render_ops.push(RenderOp::Blit {
+                                bitmap: apply_density(bitmap.clone(), state.density),
+                                x: (dev.width as isize - bitmap.w as isize) / 2,
+                                y: *y,
+                            });
+                        }
                         DrawLayer::Scroll { bitmap, y } => {
                             const MARGIN: isize = 30;
                             let scroll_w = bitmap.w as isize + MARGIN;
-                            let dupes = 1 + dev.width / scroll_w as usize;
                             render_ops.push(RenderOp::Scroll {
-                                bitmap: bitmap.clone(),
-                                x: state.scroll.x + shift_x,
+                                bitmap: apply_density(bitmap.clone(), state.density),
+                                x: state.scroll.x + shift_x as f32,
                                 y: *y + shift_y,
                                 scroll_w,
-                                dupes,
                             });
-                            let paused = state.scroll.pause_until.is_some_and(|until| time < until);
+                            let paused = frozen || state.scroll.pause_until.is_some_and(|until| time < until);
                             if !paused {
-                                state.scroll.x -= 1;
-                                if state.scroll.x <= -scroll_w {
-                                    state.scroll.x += scroll_w;
+                                state.scroll.x -= scroll_speed;
+                                if state.scroll.x <= -scroll_w as f32 {
+                                    state.scroll.x += scroll_w as f32;
                                     state.scroll.pause_until = Some(time + SCROLL_REVOLUTION_PAUSE);
                                 }
-                            } else if state.scroll.pause_until.is_some_and(|until| time >= until) {
+                            } else if !frozen && state.scroll.pause_until.is_some_and(|until| time >= until) {
                                 state.scroll.pause_until = None;
                             }
                         }
                         DrawLayer::ScrollNoShift { bitmap, y } => {
                             const MARGIN: isize = 30;
                             let scroll_w = bitmap.w as isize + MARGIN;
-                            let dupes = 1 + dev.width / scroll_w as usize;
                             render_ops.push(RenderOp::Scroll {
-                                bitmap: bitmap.clone(),
+                                bitmap: apply_density(bitmap.clone(), state.density),
                                 x: state.scroll.x,
                                 y: *y,
                                 scroll_w,
-                                dupes,
                             });
-                            let paused = state.scroll.pause_until.is_some_and(|until| time < until);
+                            let paused = frozen || state.scroll.pause_until.is_some_and(|until| time < until);
                             if !paused {
-                                state.scroll.x -= 1;
-                                if state.scroll.x <= -scroll_w {
-                                    state.scroll.x += scroll_w;
+                                state.scroll.x -= scroll_speed;
+                                if state.scroll.x <= -scroll_w as f32 {
+                                    state.scroll.x += scroll_w as f32;
                                     state.scroll.pause_until = Some(time + SCROLL_REVOLUTION_PAUSE);
                                 }
-                            } else if state.scroll.pause_until.is_some_and(|until| time >= until) {
+                            } else if !frozen && state.scroll.pause_until.is_some_and(|until| time >= until) {
                                 state.scroll.pause_until = None;
                             }
                         }
+                        DrawLayer::Slideshow { images, interval, x, y } => {
+                            let idx = if frozen {
+                                (!images.is_empty()).then(|| state.anim.ticks.saturating_sub(1) % images.len())
+                            } else {
+                                advance_slideshow_state(&mut state.anim, images.len(), *interval, time)
+                            };
+                            if let Some(idx) = idx {
+                                render_ops.push(RenderOp::Blit {
+                                    bitmap: images[idx].clone(),
+                                    x: x + shift_x,
+                                    y: y + shift_y,
+                                });
+                            }
+                        }
+                        DrawLayer::Series { values, x, y, w, h } => {
+                            let mut bitmap = Bitmap::new(*w, *h, false);
+                            bitmap.plot_series(values, 0, 0, *w, *h);
+                            render_ops.push(RenderOp::Blit {
+                                bitmap: Arc::new(bitmap),
+                                x: x + shift_x,
+                                y: y + shift_y,
+                            });
+                        }
                     }
                 }
             }
@@ -621,83 +1737,137 @@ fn run_draw_device_thread(
             for op in render_ops {
                 match op {
                     RenderOp::Blit { bitmap, x, y } => screen.blit(&bitmap, x, y, false),
-                    RenderOp::Scroll {
-                        bitmap,
-                        x,
-                        y,
-                        scroll_w,
-                        dupes,
-                    } => {
-                        for i in 0..=dupes {
-                            screen.blit(&bitmap, x + i as isize * scroll_w, y, false);
+                    RenderOp::BlitRegion { bitmap, region, x, y } => screen.blit_region(&bitmap, region, x, y, false),
+                    RenderOp::Scroll { bitmap, x, y, scroll_w } => {
+                        for copy_x in scroll_copy_positions(x, scroll_w, dev.width) {
+                            blit_subpixel(&mut screen, &bitmap, copy_x, y);
                         }
                     }
                 }
             }
 
+            if let Some(hook) = frame_hook.as_mut() {
+                hook(&mut screen);
+            }
+            if inversion.is_some_and(|(schedule, since)| schedule.is_inverted(time.duration_since(since))) {
+                screen.invert();
+            }
+            if log_next_frame || (log_frames && screen != prev_screen) {
+                log_next_frame = false;
+                info!("composed frame:\n{}", screen.to_ascii());
+            }
+
             // Draw update
-            let frame_time = Instant::now();
-            let force_redraw = frame_time.duration_since(last_frame_time) >= Duration::from_secs(1);
+            let force_redraw =
+                forced_redraw_interval.is_some_and(|interval| time.duration_since(last_frame_time) >= interval);
+            if screen != prev_screen {
+                snapshot.set(Arc::new(screen.clone()));
+            }
             if screen != prev_screen || force_redraw {
-                last_frame_time = frame_time;
-                if let Err(_err) = dev.draw(&screen, 0, 0) {
+                last_frame_time = time;
+                let draw_result = dev.draw(&screen, 0, 0);
+                status.last_draw_ok.store(draw_result.is_ok(), Ordering::Relaxed);
+                if let Err(err) = draw_result {
                     if connected {
                         connected = false;
-                        event_sender.send(DrawEvent::DeviceDisconnected).unwrap();
+                        stop_after_frame |= send_device_lost(&event_sender, Some(err)).is_err();
                     }
                 } else {
                     prev_screen = screen;
                 }
             }
+        } else if connected && blanked {
+            // Drawn once, and again after a reconnect since that resets `prev_screen`
+            let screen = Bitmap::new(dev.width, dev.height, false);
+            if screen != prev_screen {
+                snapshot.set(Arc::new(screen.clone()));
+                let draw_result = dev.draw(&screen, 0, 0);
+                status.last_draw_ok.store(draw_result.is_ok(), Ordering::Relaxed);
+                if let Err(err) = draw_result {
+                    connected = false;
+                    stop_after_frame |= send_device_lost(&event_sender, Some(err)).is_err();
+                } else {
+                    prev_screen = screen;
+                }
+            }
         }
 
         // Get device events and pass back to DrawDevice
         if connected {
-            let events = dev.get_events().unwrap_or_else(|_| {
-                connected = false;
-                event_sender.send(DrawEvent::DeviceDisconnected).unwrap();
-                vec![]
-            });
+            let events = match dev.get_events() {
+                Ok(events) => events,
+                Err(err) => {
+                    connected = false;
+                    stop_after_frame |= send_device_lost(&event_sender, Some(err)).is_err();
+                    vec![]
+                }
+            };
             for event in events {
-                event_sender.send(DrawEvent::DeviceEvent(event)).unwrap();
+                if event_sender.send(DrawEvent::DeviceEvent(event)).is_err() {
+                    stop_after_frame = true;
+                    break;
+                }
             }
         }
 
+        status.connected.store(connected, Ordering::Relaxed);
+
         // Stop
         if stop_after_frame {
             break;
         }
 
-        // Delay as long as needed based on how long frame rendering took (which will mostly depend on USB speed)
-        let frame_duration = Instant::now().duration_since(time);
-        // println!("frame: {:?}, {:?}", frame_duration, frame_delay);
-        spin_sleep::sleep(frame_delay.saturating_sub(frame_duration));
+        // Delay until the next frame is due, which depends on how long frame rendering took (mostly USB speed)
+        let now = Instant::now();
+        next_frame = next_frame_target(next_frame, frame_delay, now);
+        let delay = next_frame.saturating_duration_since(now);
+        match timing_mode {
+            TimingMode::Precise => spin_sleep::sleep(delay),
+            TimingMode::Relaxed => std::thread::sleep(delay),
+        }
     }
     dev
 }
 
 type LayerMap = BTreeMap<LayerId, DrawLayerState>;
+/// Draws layers to a `Device` on a background thread at a fixed frame rate and forwards device events.
 pub struct DrawDevice {
     width: usize,
     height: usize,
     layers: Arc<Mutex<LayerMap>>,
     layer_counter: usize,
+    layer_limit: Option<usize>,
     thread: Option<std::thread::JoinHandle<Device>>,
     cmd_sender: Sender<DrawCommand>,
     event_receiver: Receiver<DrawEvent>,
+    #[cfg_attr(not(feature = "async"), allow(dead_code))]
+    event_waker: EventWaker,
+    watcher_stop: Option<Arc<AtomicBool>>,
+    snapshot: FrameSnapshot,
+    status: Arc<DeviceStatus>,
     pub texter: TextRenderer,
 }
 
+/// Exclusive access to the layers of a `DrawDevice`, so that several changes show up in the same frame.
 pub struct LayerTxn<'a> {
     width: usize,
     height: usize,
     layer_counter: &'a mut usize,
+    layer_limit: Option<usize>,
     layers: MutexGuard<'a, LayerMap>,
     texter: &'a TextRenderer,
 }
 impl<'a> LayerTxn<'a> {
+    /// Add a layer, or return `LayerId::none()` if the layer limit is reached.
     pub fn add_layer(&mut self, layer: DrawLayer) -> LayerId {
-        add_layer_to_map(self.layer_counter, &mut self.layers, layer)
+        self.try_add_layer(layer).unwrap_or_else(LayerId::none)
+    }
+    /// Add a layer, or return `None` if the layer limit is reached.
+    pub fn try_add_layer(&mut self, layer: DrawLayer) -> Option<LayerId> {
+        try_add_layer_to_map(self.layer_counter, &mut self.layers, self.layer_limit, layer)
+    }
+    pub fn layer_count(&self) -> usize {
+        self.layers.len()
     }
     pub fn remove_layer(&mut self, id: LayerId) {
         self.layers.remove(&id);
@@ -707,6 +1877,33 @@ impl<'a> LayerTxn<'a> {
             self.layers.remove(id);
         }
     }
+    pub fn push_series_sample(&mut self, id: LayerId, value: f32) {
+        push_series_sample(&mut self.layers, id, value);
+    }
+    pub fn set_layer_bitmap(&mut self, id: LayerId, bitmap: Arc<Bitmap>) {
+        set_layer_bitmap(&mut self.layers, id, bitmap);
+    }
+    /// Replace the `old` layers with the `new` ones, pairing them up in order, and return the layers that remain.
+    /// An image layer in `old` with a transition set is kept and transitions to the bitmap and position of its new
+    /// counterpart, if that's an image layer too. Any other old layer is removed.
+    pub fn morph_layers(&mut self, old: &[LayerId], new: Vec<LayerId>) -> Vec<LayerId> {
+        morph_layers(&mut self.layers, old, new)
+    }
+    pub fn set_layer_transition(&mut self, id: LayerId, config: Option<TransitionConfig>) {
+        set_layer_transition(&mut self.layers, id, config);
+    }
+    pub fn set_layer_clip(&mut self, id: LayerId, clip: Option<(usize, usize, usize, usize)>) {
+        set_layer_clip(&mut self.layers, id, clip);
+    }
+    pub fn set_layer_density(&mut self, id: LayerId, density: f32) {
+        set_layer_density(&mut self.layers, id, density);
+    }
+    pub fn add_text(&mut self, text: &str, x: Option<isize>, y: Option<isize>) -> Vec<LayerId> {
+        self.add_text_with_mode(text, x, y, true, TextOverflowMode::Scroll)
+    }
+    pub fn add_text_no_shift(&mut self, text: &str, x: Option<isize>, y: Option<isize>) -> Vec<LayerId> {
+        self.add_text_with_mode(text, x, y, false, TextOverflowMode::Scroll)
+    }
     pub fn add_text_with_mode(
         &mut self,
         text: &str,
@@ -716,11 +1913,12 @@ impl<'a> LayerTxn<'a> {
         mode: TextOverflowMode,
     ) -> Vec<LayerId> {
         add_text_layers(
-            &self.texter,
+            self.texter,
             self.width,
             self.height,
             self.layer_counter,
             &mut self.layers,
+            self.layer_limit,
             text,
             x,
             y,
@@ -743,6 +1941,7 @@ impl<'a> LayerTxn<'a> {
             self.height,
             self.layer_counter,
             &mut self.layers,
+            self.layer_limit,
             text,
             x,
             y,
@@ -752,28 +1951,111 @@ impl<'a> LayerTxn<'a> {
     }
 }
 
+/// Collects the initial settings of a `DrawDevice`, which are all applied before its first frame.
+pub struct DrawDeviceBuilder {
+    dev: Device,
+    fps: usize,
+    texter: Option<TextRenderer>,
+    commands: Vec<DrawCommand>,
+}
+impl DrawDeviceBuilder {
+    pub fn new(dev: Device) -> Self {
+        DrawDeviceBuilder {
+            dev,
+            fps: 30,
+            texter: None,
+            commands: vec![],
+        }
+    }
+    pub fn fps(mut self, fps: usize) -> Self {
+        self.fps = fps;
+        self
+    }
+    /// Font for `add_text`. Defaults to Pixel Operator at 16px.
+    pub fn texter(mut self, texter: TextRenderer) -> Self {
+        self.texter = Some(texter);
+        self
+    }
+    pub fn shift_mode(self, mode: ShiftMode) -> Self {
+        self.command(DrawCommand::SetShiftMode(mode))
+    }
+    pub fn inversion_schedule(self, schedule: Option<InversionSchedule>) -> Self {
+        self.command(DrawCommand::SetInversionSchedule(schedule))
+    }
+    pub fn brightness(self, brightness: u8) -> Self {
+        self.command(DrawCommand::SetBrightness(brightness))
+    }
+    pub fn scroll_speed(self, pixels_per_frame: f32) -> Self {
+        self.command(DrawCommand::SetScrollSpeed(pixels_per_frame))
+    }
+    pub fn timing_mode(self, mode: TimingMode) -> Self {
+        self.command(DrawCommand::SetTimingMode(mode))
+    }
+    pub fn forced_redraw_interval(self, interval: Option<Duration>) -> Self {
+        self.command(DrawCommand::SetForcedRedrawInterval(interval))
+    }
+    /// Start drawing right away instead of waiting for `DrawDevice::play`.
+    pub fn playing(self) -> Self {
+        self.command(DrawCommand::Play)
+    }
+    fn command(mut self, cmd: DrawCommand) -> Self {
+        self.commands.push(cmd);
+        self
+    }
+    pub fn build(self) -> DrawDevice {
+        DrawDevice::start(self.dev, self.fps, self.texter, self.commands)
+    }
+}
+
 impl DrawDevice {
     pub fn new(dev: Device, fps: usize) -> DrawDevice {
+        DrawDeviceBuilder::new(dev).fps(fps).build()
+    }
+    pub fn builder(dev: Device) -> DrawDeviceBuilder {
+        DrawDeviceBuilder::new(dev)
+    }
+    // The initial commands are queued before the draw thread starts, so it handles them before drawing anything
+    fn start(dev: Device, fps: usize, texter: Option<TextRenderer>, commands: Vec<DrawCommand>) -> DrawDevice {
         let layers: Arc<Mutex<LayerMap>> = Default::default();
         let (cmd_sender, cmd_recver) = channel::<DrawCommand>();
-        let (event_sender, event_receiver) = channel::<DrawEvent>();
+        for cmd in commands {
+            cmd_sender.send(cmd).unwrap();
+        }
+        let (sender, event_receiver) = channel::<DrawEvent>();
+        let event_waker = EventWaker::default();
+        let event_sender = EventSender {
+            sender,
+            waker: event_waker.clone(),
+        };
         let c_layers = layers.clone();
+        let snapshot = FrameSnapshot::default();
+        let c_snapshot = snapshot.clone();
+        let status = Arc::new(DeviceStatus::new());
+        let c_status = status.clone();
         let (width, height) = (dev.width, dev.height);
         let thread = Some(std::thread::spawn(move || {
-            run_draw_device_thread(dev, c_layers, cmd_recver, event_sender, fps)
+            run_draw_device_thread(dev, c_layers, cmd_recver, event_sender, c_snapshot, c_status, fps)
         }));
         DrawDevice {
             width,
             height,
             layers,
             layer_counter: 0,
+            layer_limit: None,
             thread,
             cmd_sender,
             event_receiver,
-            texter: TextRenderer::new_pixel_operator(),
+            event_waker,
+            watcher_stop: None,
+            snapshot,
+            status,
+            texter: texter.unwrap_or_else(TextRenderer::new_pixel_operator),
         }
     }
     fn destroy(&mut self) -> Option<Device> {
+        if let Some(stop) = self.watcher_stop.take() {
+            stop.store(true, Ordering::Relaxed);
+        }
         if let Some(thread) = self.thread.take() {
             self.cmd_sender.send(DrawCommand::Stop).unwrap();
             Some(thread.join().unwrap())
@@ -784,26 +2066,99 @@ impl DrawDevice {
     pub fn stop(mut self) -> Device {
         self.destroy().unwrap()
     }
+    /// Poll the USB device list on a separate thread, so that unplugging and replugging the device is noticed
+    /// right away rather than on the next failed draw. Without this, only failed draws detect disconnects.
+    pub fn watch_device(&mut self) {
+        if self.watcher_stop.is_some() {
+            return;
+        }
+        let stop = Arc::new(AtomicBool::new(false));
+        self.watcher_stop = Some(stop.clone());
+        let cmd_sender = self.cmd_sender.clone();
+        std::thread::spawn(move || {
+            let Ok(mut watcher) = DeviceWatcher::new() else {
+                return;
+            };
+            let mut present = true;
+            while !stop.load(Ordering::Relaxed) {
+                std::thread::sleep(DEVICE_WATCH_PERIOD);
+                let Ok(now_present) = watcher.is_present() else {
+                    continue;
+                };
+                if now_present != present {
+                    present = now_present;
+                    if cmd_sender.send(DrawCommand::SetDevicePresent(present)).is_err() {
+                        break;
+                    }
+                }
+            }
+        });
+    }
     pub fn try_event(&mut self) -> Option<DrawEvent> {
         self.event_receiver.try_recv().ok()
     }
     pub fn poll_event(&mut self) -> DrawEvent {
         self.event_receiver.recv().unwrap()
     }
+    /// Wait for the next event without blocking the thread, for use in async code. Works with any async runtime.
+    /// Returns `None` once the draw thread has stopped.
+    #[cfg(feature = "async")]
+    pub async fn next_event(&mut self) -> Option<DrawEvent> {
+        std::future::poll_fn(|cx| {
+            // Register before checking, so that an event sent in between still wakes this task
+            *self.event_waker.lock().unwrap_or_else(PoisonError::into_inner) = Some(cx.waker().clone());
+            match self.event_receiver.try_recv() {
+                Ok(event) => std::task::Poll::Ready(Some(event)),
+                Err(TryRecvError::Empty) => std::task::Poll::Pending,
+                Err(TryRecvError::Disconnected) => std::task::Poll::Ready(None),
+            }
+        })
+        .await
+    }
+    /// Handle to the frame that is currently on the screen.
+    pub fn frame_snapshot(&self) -> FrameSnapshot {
+        self.snapshot.clone()
+    }
+    /// Whether the device is currently connected, as last seen by the draw thread.
+    pub fn is_connected(&self) -> bool {
+        self.status.connected.load(Ordering::Relaxed)
+    }
+    /// Whether the most recent frame sent to the device was drawn successfully.
+    pub fn last_draw_ok(&self) -> bool {
+        self.status.last_draw_ok.load(Ordering::Relaxed)
+    }
+    pub fn width(&self) -> usize {
+        self.width
+    }
+    pub fn height(&self) -> usize {
+        self.height
+    }
+    /// X position that centers something `w` pixels wide on the screen.
+    pub fn center_x(&self, w: usize) -> isize {
+        (self.width as isize - w as isize) / 2
+    }
+    /// Y position that centers something `h` pixels tall on the screen.
+    pub fn center_y(&self, h: usize) -> isize {
+        (self.height as isize - h as isize) / 2
+    }
     pub fn center_bitmap(&self, bitmap: &Bitmap) -> (isize, isize) {
-        (
-            (self.width as isize - bitmap.w as isize) / 2,
-            (self.height as isize - bitmap.h as isize) / 2,
-        )
+        (self.center_x(bitmap.w), self.center_y(bitmap.h))
+    }
+    /// Add an image in the middle of the screen, unaffected by OLED shifting.
+    pub fn add_image_centered(&mut self, bitmap: Arc<Bitmap>) -> LayerId {
+        let (x, y) = self.center_bitmap(&bitmap);
+        self.add_layer(DrawLayer::ImageNoShift { bitmap, x, y })
     }
 
+    #[doc(hidden)]
     pub fn add_text_with_font(&mut self, text: &str, font: &TextRenderer, x: isize, y: isize) -> Vec<LayerId> {
         add_text_layers(
             font,
             self.width,
             self.height,
             &mut self.layer_counter,
-            &mut self.layers.lock().unwrap(),
+            &mut lock_layers(&self.layers),
+            self.layer_limit,
             text,
             Some(x),
             Some(y),
@@ -811,21 +2166,81 @@ impl DrawDevice {
             TextOverflowMode::Clip,
         )
     }
+    /// Add a layer, or return `LayerId::none()` if the layer limit is reached.
     pub fn add_layer(&mut self, layer: DrawLayer) -> LayerId {
-        let mut layers = self.layers.lock().unwrap();
-        add_layer_to_map(&mut self.layer_counter, &mut layers, layer)
+        self.try_add_layer(layer).unwrap_or_else(LayerId::none)
+    }
+    /// Add a layer, or return `None` if the layer limit is reached.
+    pub fn try_add_layer(&mut self, layer: DrawLayer) -> Option<LayerId> {
+        let mut layers = lock_layers(&self.layers);
+        try_add_layer_to_map(&mut self.layer_counter, &mut layers, self.layer_limit, layer)
+    }
+    pub fn layer_count(&self) -> usize {
+        lock_layers(&self.layers).len()
+    }
+    /// Stop `add_layer`, the text methods and `restore_state` from adding more layers once `limit` layers exist,
+    /// so that a runaway client can't slow down rendering, which goes through every layer each frame.
+    /// There is no limit by default.
+    pub fn set_layer_limit(&mut self, limit: Option<usize>) {
+        self.layer_limit = limit;
+    }
+    /// Decode an image file and add it centered on the screen, turning on pixels at or above `threshold`.
+    /// GIFs are added as an animation playing at their own frame delays.
+    pub fn show_image(&mut self, path: impl AsRef<Path>, threshold: u8) -> anyhow::Result<LayerId> {
+        let layer = image_file_layer(path.as_ref(), threshold, self.width(), self.height())?;
+        self.try_add_layer(layer).context("layer limit reached")
     }
     pub fn remove_layer(&mut self, id: LayerId) {
-        self.layers.lock().unwrap().remove(&id);
+        lock_layers(&self.layers).remove(&id);
     }
     pub fn remove_layers(&mut self, ids: &[LayerId]) {
-        let mut layers = self.layers.lock().unwrap();
+        let mut layers = lock_layers(&self.layers);
         for id in ids {
             layers.remove(id);
         }
     }
     pub fn clear_layers(&mut self) {
-        self.layers.lock().unwrap().clear();
+        lock_layers(&self.layers).clear();
+    }
+    /// Append a sample to a `DrawLayer::Series` layer. The oldest samples are dropped once the layer is full.
+    pub fn push_series_sample(&mut self, id: LayerId, value: f32) {
+        push_series_sample(&mut lock_layers(&self.layers), id, value);
+    }
+    /// Replace the bitmap of an image or scroll layer in place, keeping its position.
+    /// Image layers with a transition set animate from the old bitmap to the new one.
+    pub fn set_layer_bitmap(&mut self, id: LayerId, bitmap: Arc<Bitmap>) {
+        set_layer_bitmap(&mut lock_layers(&self.layers), id, bitmap);
+    }
+    /// Set or clear the transition used when the layer's bitmap is replaced. Layers have no transition by default.
+    pub fn set_layer_transition(&mut self, id: LayerId, config: Option<TransitionConfig>) {
+        set_layer_transition(&mut lock_layers(&self.layers), id, config);
+    }
+    /// Only show the `(x, y, w, h)` area of an image layer's bitmap, drawn at the layer's position.
+    /// Moving the area over time pans across a large bitmap without cropping it each time.
+    pub fn set_layer_clip(&mut self, id: LayerId, clip: Option<(usize, usize, usize, usize)>) {
+        set_layer_clip(&mut lock_layers(&self.layers), id, clip);
+    }
+    /// Dim an image, text or scroll layer by only lighting about `density` (clamped to `0.0..=1.0`) of its pixels
+    /// in an even pattern, e.g. `0.5` or `0.25` for faint secondary content. Layers start at `1.0`.
+    pub fn set_layer_density(&mut self, id: LayerId, density: f32) {
+        set_layer_density(&mut lock_layers(&self.layers), id, density);
+    }
+    /// All current layers in draw order, e.g. to compare layouts in tests or to restore them later.
+    pub fn export_state(&self) -> Vec<LayerSnapshot> {
+        export_layers(&lock_layers(&self.layers))
+    }
+    /// Replace all layers with ones from `export_state`. Animations and scrolling restart from the beginning.
+    pub fn restore_state(&mut self, snapshot: Vec<LayerSnapshot>) {
+        restore_layers(
+            &mut self.layer_counter,
+            &mut lock_layers(&self.layers),
+            self.layer_limit,
+            snapshot,
+        );
+    }
+    /// Replace the default font. Text that's already shown keeps the font it was added with.
+    pub fn set_texter(&mut self, texter: TextRenderer) {
+        self.texter = texter;
     }
     pub fn font_line_height(&self) -> usize {
         self.texter.line_height()
@@ -833,17 +2248,29 @@ impl DrawDevice {
     pub fn measure_line_widths(&self, text: &str) -> Vec<usize> {
         self.texter.measure_line_widths(text)
     }
+    /// Run `f` with the layers locked, so that all changes made in it are shown at once.
+    /// The draw thread holds the same lock while reading the layers for a frame, so no frame ever shows only
+    /// part of the changes. Separate calls like `add_layer` may each end up in a different frame.
     pub fn transact_layers<R>(&mut self, f: impl FnOnce(&mut LayerTxn<'_>) -> R) -> R {
-        let layers = self.layers.lock().unwrap();
+        let layers = lock_layers(&self.layers);
         let mut txn = LayerTxn {
             width: self.width,
             height: self.height,
             layer_counter: &mut self.layer_counter,
+            layer_limit: self.layer_limit,
             layers,
             texter: &self.texter,
         };
         f(&mut txn)
     }
+    /// Remove the `old` layers and add new ones with `f` in a single transaction, e.g. to swap out a notification
+    /// without a frame in between that shows neither the old nor the new one.
+    pub fn replace_layers<R>(&mut self, old: &[LayerId], f: impl FnOnce(&mut LayerTxn<'_>) -> R) -> R {
+        self.transact_layers(|txn| {
+            txn.remove_layers(old);
+            f(txn)
+        })
+    }
     pub fn add_text_with_mode(
         &mut self,
         text: &str,
@@ -852,13 +2279,14 @@ impl DrawDevice {
         shift: bool,
         mode: TextOverflowMode,
     ) -> Vec<LayerId> {
-        let mut layers = self.layers.lock().unwrap();
+        let mut layers = lock_layers(&self.layers);
         add_text_layers(
             &self.texter,
             self.width,
             self.height,
             &mut self.layer_counter,
             &mut layers,
+            self.layer_limit,
             text,
             x,
             y,
@@ -875,15 +2303,71 @@ impl DrawDevice {
     pub fn set_shift_mode(&mut self, mode: ShiftMode) {
         self.cmd_sender.send(DrawCommand::SetShiftMode(mode)).unwrap();
     }
+    /// Set or clear a schedule for briefly inverting the screen. The first inversion starts `duration` before
+    /// `interval` has passed from now. Nothing is inverted by default.
+    pub fn set_inversion_schedule(&mut self, schedule: Option<InversionSchedule>) {
+        self.cmd_sender
+            .send(DrawCommand::SetInversionSchedule(schedule))
+            .unwrap();
+    }
+    /// Set how many pixels scrolling text moves per frame. Fractional speeds are drawn with dithered edges.
+    pub fn set_scroll_speed(&mut self, pixels_per_frame: f32) {
+        self.cmd_sender
+            .send(DrawCommand::SetScrollSpeed(pixels_per_frame))
+            .unwrap();
+    }
+    /// Run `hook` on every composed frame just before it's sent, for effects that aren't worth a layer type,
+    /// like scanlines or debug overlays. It runs on the draw thread for every rendered frame, so it should be fast:
+    /// anything slow directly lowers the frame rate. Replaces any previous hook.
+    pub fn on_frame(&mut self, hook: Box<dyn FnMut(&mut Bitmap) + Send>) {
+        self.cmd_sender.send(DrawCommand::SetFrameHook(Some(hook))).unwrap();
+    }
+    pub fn clear_on_frame(&mut self) {
+        self.cmd_sender.send(DrawCommand::SetFrameHook(None)).unwrap();
+    }
+    /// Log the next composed frame as ASCII art through `tracing`, e.g. to paste into a bug report.
+    /// Setting the `GGOLED_LOG_FRAMES` environment variable logs every frame that changes instead.
+    pub fn log_frame(&mut self) {
+        self.cmd_sender.send(DrawCommand::LogFrame).unwrap();
+    }
+    /// Set how often an unchanged frame is sent again, to recover from frames the device dropped.
+    /// Defaults to once a second. `None` only sends frames when they change, avoiding USB writes for static content.
+    pub fn set_forced_redraw_interval(&mut self, interval: Option<Duration>) {
+        self.cmd_sender
+            .send(DrawCommand::SetForcedRedrawInterval(interval))
+            .unwrap();
+    }
+    pub fn set_timing_mode(&mut self, mode: TimingMode) {
+        self.cmd_sender.send(DrawCommand::SetTimingMode(mode)).unwrap();
+    }
     pub fn set_volume(&mut self, volume: u8) {
         self.cmd_sender.send(DrawCommand::SetVolume(volume)).unwrap();
     }
+    /// Set screen brightness, 1-10, clamping values outside of that. Kept across reconnects.
+    pub fn set_brightness(&mut self, brightness: u8) {
+        self.cmd_sender.send(DrawCommand::SetBrightness(brightness)).unwrap();
+    }
+    /// Start drawing to the device.
     pub fn play(&mut self) {
         self.cmd_sender.send(DrawCommand::Play).unwrap();
     }
+    /// Stop drawing to the device entirely. Whatever was drawn last stays on the screen until `play` is called.
     pub fn pause(&mut self) {
         self.cmd_sender.send(DrawCommand::Pause).unwrap();
     }
+    /// Stop drawing like `pause`, but leave the screen blank, also after the device reconnects, until `play` is called.
+    pub fn blank(&mut self) {
+        self.cmd_sender.send(DrawCommand::Blank).unwrap();
+    }
+    /// Send the next frame to the device even if it hasn't changed, e.g. when the screen may have been corrupted.
+    /// Unlike waiting for the forced redraw interval, this takes effect on the next frame.
+    pub fn refresh(&mut self) {
+        self.cmd_sender.send(DrawCommand::ForceRedraw).unwrap();
+    }
+    /// Stop or resume animations, slideshows and scrolling text, while still drawing any layer changes.
+    pub fn freeze_motion(&mut self, freeze: bool) {
+        self.cmd_sender.send(DrawCommand::FreezeMotion(freeze)).unwrap();
+    }
 }
 impl Drop for DrawDevice {
     fn drop(&mut self) {
@@ -895,6 +2379,19 @@ impl Drop for DrawDevice {
 mod tests {
     use super::*;
 
+    #[test]
+    fn album_art_loads_from_file_url() {
+        let path = std::env::temp_dir().join(format!("ggoled art {}.png", std::process::id()));
+        std::fs::write(&path, bitmap_to_png(&Bitmap::new(8, 4, true)).unwrap()).unwrap();
+        let url = format!("file://{}", path.display()).replace(' ', "%20");
+        let art = load_album_art(&url, 4);
+        std::fs::remove_file(&path).unwrap();
+        let art = art.unwrap();
+        assert_eq!((art.w, art.h), (4, 2));
+        assert!(art.data.all());
+        assert!(load_album_art("ftp://example.com/art.png", 4).is_err());
+    }
+
     fn frame(delay: Option<Duration>) -> Frame {
         Frame {
             bitmap: Arc::new(Bitmap::new(1, 1, true)),
@@ -902,6 +2399,444 @@ mod tests {
         }
     }
 
+    #[test]
+    fn bdf_scale_rounds_to_whole_factor() {
+        assert_eq!(bdf_scale(13, 13.0), 1);
+        assert_eq!(bdf_scale(13, 16.0), 1);
+        assert_eq!(bdf_scale(13, 26.0), 2);
+        assert_eq!(bdf_scale(13, 32.0), 2);
+        assert_eq!(bdf_scale(13, 4.0), 1);
+        assert_eq!(bdf_scale(0, 16.0), 16);
+    }
+
+    #[test]
+    fn scaled_bdf_text_is_measured_and_rendered_larger() {
+        let texter = TextRenderer::new_merged();
+        let FontInner::Bdf { font, .. } = texter.inner.clone() else {
+            unreachable!();
+        };
+        let scaled = TextRenderer::from_inner(FontInner::Bdf { font, scale: 2 });
+        let line = &texter.render_lines("Hi")[0];
+        let scaled_line = &scaled.render_lines("Hi")[0];
+        assert_eq!(scaled.line_height(), texter.line_height() * 2);
+        assert_eq!(scaled.measure_line_widths("Hi"), vec![line.w * 2]);
+        assert_eq!(*scaled_line, line.scale(2));
+        assert_eq!(scaled.render_lines("")[0].h, texter.line_height() * 2);
+    }
+
+    struct CountingWaker(std::sync::atomic::AtomicUsize);
+    impl std::task::Wake for CountingWaker {
+        fn wake(self: Arc<Self>) {
+            self.0.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    #[test]
+    fn event_sender_wakes_waiting_task_on_send_and_drop() {
+        let counter = Arc::new(CountingWaker(Default::default()));
+        let (sender, receiver) = channel();
+        let waker = EventWaker::default();
+        let event_sender = EventSender {
+            sender,
+            waker: waker.clone(),
+        };
+        // Nothing waiting yet
+        event_sender.send(DrawEvent::DeviceReconnected).unwrap();
+        assert_eq!(counter.0.load(Ordering::Relaxed), 0);
+
+        *waker.lock().unwrap() = Some(Waker::from(counter.clone()));
+        event_sender.send(DrawEvent::DeviceDisconnected).unwrap();
+        assert_eq!(counter.0.load(Ordering::Relaxed), 1);
+        assert_eq!(receiver.try_iter().count(), 2);
+
+        *waker.lock().unwrap() = Some(Waker::from(counter.clone()));
+        drop(event_sender);
+        assert_eq!(counter.0.load(Ordering::Relaxed), 2);
+        assert!(matches!(receiver.try_recv(), Err(TryRecvError::Disconnected)));
+    }
+
+    #[test]
+    fn ellipsis_truncates_lines_to_fit() {
+        let texter = TextRenderer::new_pixel_operator();
+        let long = "A rather long song title that won't fit";
+        let truncated = texter.truncate_with_ellipsis(long, 64);
+        assert!(texter.measure_line_widths(&truncated)[0] <= 64);
+        assert!(truncated.len() < long.len());
+        assert!(truncated.ends_with('…') || truncated.ends_with("..."));
+        assert_eq!(texter.truncate_with_ellipsis("Hi", 64), "Hi");
+        assert_eq!(texter.truncate_with_ellipsis(long, 1), "");
+    }
+
+    #[test]
+    fn ellipsis_text_layers_fit_the_screen() {
+        let texter = TextRenderer::new_pixel_operator();
+        let mut layers = LayerMap::new();
+        let mut counter = 0;
+        let text = "A rather long song title that won't fit\nShort";
+        let ids = add_text_layers(
+            &texter,
+            128,
+            64,
+            &mut counter,
+            &mut layers,
+            None,
+            text,
+            None,
+            None,
+            true,
+            TextOverflowMode::Ellipsis,
+        );
+        assert_eq!(ids.len(), 2);
+        for id in ids {
+            let DrawLayer::Image { bitmap, x, .. } = &layers[&id].layer else {
+                panic!("expected an image layer");
+            };
+            assert!(*x >= 0 && *x as usize + bitmap.w <= 128);
+        }
+    }
+
+    #[test]
+    fn inversion_schedule_inverts_at_the_end_of_each_interval() {
+        let schedule = InversionSchedule {
+            interval: Duration::from_secs(60),
+            duration: Duration::from_secs(3),
+        };
+        let inverted = |secs: u64| schedule.is_inverted(Duration::from_secs(secs));
+        assert!(!inverted(0) && !inverted(56) && !inverted(60) && !inverted(116));
+        assert!(inverted(57) && inverted(59) && inverted(117));
+        let never = InversionSchedule {
+            interval: Duration::ZERO,
+            duration: Duration::from_secs(3),
+        };
+        assert!(!never.is_inverted(Duration::from_secs(1)));
+    }
+
+    #[test]
+    fn scroll_copies_cover_the_screen_without_gaps() {
+        for (bitmap_w, width) in [(1, 128), (4, 8), (200, 128), (10, 1)] {
+            let scroll_w = bitmap_w + 30;
+            for x in [1.0, 0.5, 0.0, -0.5, -(scroll_w as f32) + 0.5, -(scroll_w as f32)] {
+                let copies: Vec<f32> = scroll_copy_positions(x, scroll_w, width).collect();
+                assert!(copies[0] <= 0.0 && copies[0] > -(scroll_w as f32), "{copies:?}");
+                assert!(copies.windows(2).all(|pair| pair[1] - pair[0] == scroll_w as f32));
+                let last = copies.last().unwrap();
+                assert!(
+                    *last < width as f32 && last + scroll_w as f32 >= width as f32,
+                    "{copies:?}"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn layer_limit_rejects_new_layers() {
+        let mut layers = LayerMap::new();
+        let mut counter = 0;
+        let layer = DrawLayer::Image {
+            bitmap: Arc::new(Bitmap::new(1, 1, true)),
+            x: 0,
+            y: 0,
+        };
+        for _ in 0..2 {
+            assert!(try_add_layer_to_map(&mut counter, &mut layers, Some(2), layer.clone()).is_some());
+        }
+        assert_eq!(
+            try_add_layer_to_map(&mut counter, &mut layers, Some(2), layer.clone()),
+            None
+        );
+        assert_eq!(layers.len(), 2);
+        assert!(try_add_layer_to_map(&mut counter, &mut layers, None, layer).is_some());
+    }
+
+    #[test]
+    fn layer_limit_applies_to_text_and_restored_layers() {
+        let texter = TextRenderer::new_pixel_operator();
+        let mut layers = LayerMap::new();
+        let mut counter = 0;
+        let ids = add_text_layers(
+            &texter,
+            128,
+            64,
+            &mut counter,
+            &mut layers,
+            Some(2),
+            "One\nTwo\nThree",
+            None,
+            None,
+            true,
+            TextOverflowMode::Clip,
+        );
+        assert_eq!(ids.len(), 2);
+        assert!(ids.iter().all(|id| layers.contains_key(id)));
+        assert_eq!(layers.len(), 2);
+
+        let snapshot = export_layers(&layers);
+        let mut restored = LayerMap::new();
+        restore_layers(&mut counter, &mut restored, Some(1), snapshot.clone());
+        assert_eq!(export_layers(&restored), snapshot[..1]);
+    }
+
+    #[test]
+    fn image_file_layer_centers_the_image() {
+        let path = std::env::temp_dir().join(format!("ggoled_image_file_layer_{}.png", std::process::id()));
+        std::fs::write(&path, bitmap_to_png(&Bitmap::new(8, 4, true)).unwrap()).unwrap();
+        let layer = image_file_layer(&path, 0x80, 128, 64);
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(
+            layer.unwrap(),
+            DrawLayer::Image {
+                bitmap: Arc::new(Bitmap::new(8, 4, true)),
+                x: 60,
+                y: 30
+            }
+        );
+        assert!(image_file_layer(&path, 0x80, 128, 64).is_err());
+    }
+
+    #[test]
+    fn frames_from_memory_decodes_gif_frames_with_delays() {
+        let mut buf = vec![];
+        {
+            let mut encoder = image::codecs::gif::GifEncoder::new(&mut buf);
+            for on in [true, false] {
+                let luma = if on { 0xff } else { 0x00 };
+                let img = image::RgbaImage::from_pixel(2, 2, image::Rgba([luma, luma, luma, 0xff]));
+                let delay = image::Delay::from_numer_denom_ms(100, 1);
+                encoder
+                    .encode_frame(image::Frame::from_parts(img, 0, 0, delay))
+                    .unwrap();
+            }
+        }
+        let frames = frames_from_memory(&buf, 0x80).unwrap();
+        assert_eq!(frames.len(), 2);
+        assert_eq!(*frames[0].bitmap, Bitmap::new(2, 2, true));
+        assert_eq!(*frames[1].bitmap, Bitmap::new(2, 2, false));
+        assert!(frames
+            .iter()
+            .all(|frame| frame.delay == Some(Duration::from_millis(100))));
+
+        let png = bitmap_to_png(&Bitmap::new(3, 1, true)).unwrap();
+        let frames = frames_from_memory(&png, 0x80).unwrap();
+        assert_eq!(
+            frames,
+            vec![Frame {
+                bitmap: Arc::new(Bitmap::new(3, 1, true)),
+                delay: None
+            }]
+        );
+    }
+
+    #[test]
+    fn badge_knocks_text_out_of_a_rounded_background() {
+        let texter = TextRenderer::new_pixel_operator();
+        let line = texter.render_lines("42").remove(0);
+        let badge = texter.render_badge("42");
+        assert_eq!((badge.w, badge.h), (line.w + 4, line.h + 2));
+        let px = |x: usize, y: usize| badge.data[x + y * badge.w];
+        for (x, y) in [(0, 0), (badge.w - 1, 0), (0, badge.h - 1), (badge.w - 1, badge.h - 1)] {
+            assert!(!px(x, y));
+        }
+        assert!(px(1, 0) && px(0, 1));
+        for y in 0..line.h {
+            for x in 0..line.w {
+                assert_eq!(px(x + 2, y + 1), !line.data[x + y * line.w]);
+            }
+        }
+    }
+
+    #[test]
+    fn clipped_text_wider_than_screen_is_anchored_left() {
+        let texter = TextRenderer::new_pixel_operator();
+        let mut layers = LayerMap::new();
+        let mut counter = 0;
+        let ids = add_text_layers(
+            &texter,
+            32,
+            64,
+            &mut counter,
+            &mut layers,
+            None,
+            "Far too wide\nHi",
+            None,
+            None,
+            true,
+            TextOverflowMode::Clip,
+        );
+        let x = |id: &LayerId| match &layers[id].layer {
+            DrawLayer::Image { bitmap, x, .. } => (*x, bitmap.w),
+            _ => panic!("expected an image layer"),
+        };
+        assert_eq!(x(&ids[0]).0, 0);
+        let (short_x, short_w) = x(&ids[1]);
+        assert_eq!(short_x, (32 - short_w as isize) / 2);
+    }
+
+    #[test]
+    fn scroll_together_pads_lines_to_the_same_width() {
+        let texter = TextRenderer::new_pixel_operator();
+        let mut layers = LayerMap::new();
+        let mut counter = 0;
+        let mut add = |text: &str| {
+            add_text_layers(
+                &texter,
+                32,
+                64,
+                &mut counter,
+                &mut layers,
+                None,
+                text,
+                None,
+                None,
+                true,
+                TextOverflowMode::ScrollTogether,
+            )
+        };
+        let scrolling = add("Far too wide\nHi");
+        let fitting = add("Hi\nHo");
+        let width = |id: &LayerId| match &layers[id].layer {
+            DrawLayer::Scroll { bitmap, .. } => Some(bitmap.w),
+            _ => None,
+        };
+        let long_w = width(&scrolling[0]).expect("expected a scroll layer");
+        assert!(long_w >= 32);
+        assert_eq!(width(&scrolling[1]), Some(long_w));
+        assert!(fitting.iter().all(|id| width(id).is_none()));
+    }
+
+    #[test]
+    fn embolden_widens_strokes_by_a_pixel() {
+        let bitmap = Bitmap::from_ascii(&["#..#", ".#.."], '#');
+        assert_eq!(embolden(&bitmap), Bitmap::from_ascii(&["##.##", ".##.."], '#'));
+        assert_eq!(embolden(&Bitmap::new(0, 3, false)).w, 0);
+    }
+
+    #[test]
+    fn bold_text_measures_like_it_renders() {
+        let mut texter = TextRenderer::new_pixel_operator();
+        let normal = texter.measure_line_widths("Hello\n\nWorld");
+        texter.set_weight(FontWeight::Bold);
+        let widths = texter.measure_line_widths("Hello\n\nWorld");
+        let lines = texter.render_lines("Hello\n\nWorld");
+        assert_eq!(widths, lines.iter().map(|line| line.w).collect::<Vec<_>>());
+        assert_eq!(widths, vec![normal[0] + 1, 0, normal[2] + 1]);
+    }
+
+    #[test]
+    fn dithered_glyphs_render_partial_coverage_as_patterns() {
+        // Pixel Operator is drawn off its pixel grid at 11px, so its edges are anti-aliased
+        let mut texter = TextRenderer::from_inner(FontInner::Ttf {
+            font: Font::try_from_bytes(include_bytes!("../fonts/PixelOperator.ttf")).unwrap(),
+            size: 11.0,
+        });
+        let plain = texter.render_lines("Ag").remove(0);
+        texter.set_glyph_rendering(GlyphRendering::Dithered);
+        let dithered = texter.render_lines("Ag").remove(0);
+        // A plain threshold loses most of the "A", while dithering keeps its shape
+        let expected_plain = Bitmap::from_ascii(
+            &[
+                "..........",
+                "..........",
+                "...#..###.",
+                "...#....#.",
+                ".###....#.",
+                "...#....#.",
+                "...#....#.",
+                "........#.",
+                "..........",
+                "..........",
+            ],
+            '#',
+        );
+        let expected_dithered = Bitmap::from_ascii(
+            &[
+                "..#.......",
+                ".#.#......",
+                "#.....###.",
+                "...#.#....",
+                "#####.#.#.",
+                ".#.#.#....",
+                "#.....#.#.",
+                "..........",
+                "......#.#.",
+                "..........",
+            ],
+            '#',
+        );
+        assert_eq!(plain.to_ascii(), expected_plain.to_ascii());
+        assert_eq!(dithered.to_ascii(), expected_dithered.to_ascii());
+        assert_eq!(texter.measure_line_widths("Ag")[0], dithered.w);
+    }
+
+    #[test]
+    fn density_thins_lit_pixels_evenly() {
+        let bitmap = Arc::new(Bitmap::new(8, 8, true));
+        let count = |b: &Bitmap| b.data.iter().filter(|on| *on).count();
+        assert!(Arc::ptr_eq(&apply_density(bitmap.clone(), 1.0), &bitmap));
+        assert_eq!(count(&apply_density(bitmap.clone(), 0.5)), 32);
+        assert_eq!(count(&apply_density(bitmap.clone(), 0.25)), 16);
+        assert_eq!(count(&apply_density(bitmap.clone(), 0.0)), 0);
+        assert_eq!(count(&apply_density(Arc::new(Bitmap::new(8, 8, false)), 0.5)), 0);
+    }
+
+    #[test]
+    fn exported_layers_restore_with_same_ids_and_settings() {
+        let mut layers = LayerMap::new();
+        let mut counter = 0;
+        let bitmap = Arc::new(Bitmap::from_ascii(&["#.", ".#"], '#'));
+        let image = add_layer_to_map(&mut counter, &mut layers, DrawLayer::Image { bitmap, x: 3, y: 4 });
+        let series = DrawLayer::Series {
+            values: vec![0.5],
+            x: 0,
+            y: 0,
+            w: 4,
+            h: 4,
+        };
+        add_layer_to_map(&mut counter, &mut layers, series);
+        set_layer_clip(&mut layers, image, Some((0, 0, 1, 1)));
+        set_layer_density(&mut layers, image, 0.5);
+        let snapshot = export_layers(&layers);
+        assert_eq!(snapshot.len(), 2);
+        assert_eq!(snapshot[0].id, image);
+        assert_eq!(snapshot[0].clip, Some((0, 0, 1, 1)));
+
+        let mut restored = LayerMap::new();
+        let mut restored_counter = 0;
+        restore_layers(&mut restored_counter, &mut restored, None, snapshot.clone());
+        assert_eq!(export_layers(&restored), snapshot);
+        // New layers don't reuse restored ids
+        let next = add_layer_to_map(
+            &mut restored_counter,
+            &mut restored,
+            DrawLayer::Scroll {
+                bitmap: Arc::new(Bitmap::new(1, 1, true)),
+                y: 0,
+            },
+        );
+        assert!(!snapshot.iter().any(|layer| layer.id == next));
+    }
+
+    #[test]
+    fn layer_density_is_clamped() {
+        let mut layers = LayerMap::new();
+        let mut counter = 0;
+        let bitmap = Arc::new(Bitmap::new(1, 1, true));
+        let id = add_layer_to_map(&mut counter, &mut layers, DrawLayer::Image { bitmap, x: 0, y: 0 });
+        set_layer_density(&mut layers, id, 2.0);
+        assert_eq!(layers[&id].density, 1.0);
+        set_layer_density(&mut layers, id, -1.0);
+        assert_eq!(layers[&id].density, 0.0);
+    }
+
+    #[test]
+    fn frame_delay_keeps_fractional_milliseconds() {
+        let delay = frame_delay(image::Delay::from_numer_denom_ms(100, 3));
+        assert_eq!(delay, Duration::from_nanos(33_333_333));
+        assert_eq!(
+            frame_delay(image::Delay::from_numer_denom_ms(40, 1)),
+            Duration::from_millis(40)
+        );
+    }
+
     #[test]
     fn advance_animation_clamps_zero_delay() {
         let now = Instant::now();
@@ -948,4 +2883,323 @@ mod tests {
         assert_eq!(anim.ticks, MAX_ANIM_CATCHUP_STEPS);
         assert!(anim.next_update >= now + fallback);
     }
+
+    #[test]
+    fn lock_layers_recovers_from_poisoning() {
+        let layers: Arc<Mutex<LayerMap>> = Default::default();
+        let c_layers = layers.clone();
+        let result = std::thread::spawn(move || {
+            let _guard = c_layers.lock().unwrap();
+            panic!("poison the layers");
+        })
+        .join();
+        assert!(result.is_err());
+        assert!(layers.is_poisoned());
+        let mut counter = 0;
+        let id = add_layer_to_map(
+            &mut counter,
+            &mut lock_layers(&layers),
+            DrawLayer::Image {
+                bitmap: Arc::new(Bitmap::new(1, 1, true)),
+                x: 0,
+                y: 0,
+            },
+        );
+        assert!(lock_layers(&layers).contains_key(&id));
+    }
+
+    #[test]
+    fn next_frame_target_keeps_average_rate() {
+        let start = Instant::now();
+        let delay = Duration::from_millis(10);
+        // On time: the next frame is one delay later
+        let target = next_frame_target(start, delay, start + Duration::from_millis(2));
+        assert_eq!(target, start + delay);
+        // A slow frame doesn't push back the frames after it
+        let target = next_frame_target(target, delay, start + Duration::from_millis(25));
+        assert_eq!(target, start + delay * 2);
+        let target = next_frame_target(target, delay, start + Duration::from_millis(26));
+        assert_eq!(target, start + delay * 3);
+        // A long stall restarts the schedule instead of rushing through missed frames
+        let now = start + Duration::from_secs(5);
+        assert_eq!(next_frame_target(target, delay, now), now);
+    }
+
+    #[test]
+    fn reconnect_delay_backs_off_up_to_cap() {
+        assert_eq!(reconnect_delay(0, 0.0), RECONNECT_PERIOD);
+        assert_eq!(reconnect_delay(1, 0.0), RECONNECT_PERIOD * 2);
+        assert_eq!(reconnect_delay(3, 0.0), RECONNECT_PERIOD * 8);
+        assert_eq!(reconnect_delay(10, 0.0), MAX_RECONNECT_PERIOD);
+        assert_eq!(reconnect_delay(u32::MAX, 0.0), MAX_RECONNECT_PERIOD);
+        assert!(reconnect_delay(10, 1.0) <= MAX_RECONNECT_PERIOD.mul_f64(1.0 + RECONNECT_JITTER));
+        assert!(reconnect_delay(0, 0.5) > RECONNECT_PERIOD);
+    }
+
+    #[test]
+    fn reconnect_forces_a_full_redraw() {
+        let screen = Bitmap::new(128, 64, true);
+        let mut prev_screen = screen.clone();
+        assert!(!reconnect_for_redraw(|| bail!("still unplugged"), &mut prev_screen));
+        assert!(prev_screen == screen);
+
+        // An unchanged frame counts as new after reconnecting, so it's sent to the possibly reset screen
+        assert!(reconnect_for_redraw(|| Ok(()), &mut prev_screen));
+        assert!(screen != prev_screen);
+    }
+
+    #[test]
+    fn push_series_sample_trims_to_width() {
+        let mut layers = LayerMap::new();
+        let mut counter = 0;
+        let id = add_layer_to_map(
+            &mut counter,
+            &mut layers,
+            DrawLayer::Series {
+                values: vec![],
+                x: 0,
+                y: 0,
+                w: 2,
+                h: 4,
+            },
+        );
+        for v in [1.0, 2.0, 3.0] {
+            push_series_sample(&mut layers, id, v);
+        }
+        let Some(DrawLayerState {
+            layer: DrawLayer::Series { values, .. },
+            ..
+        }) = layers.get(&id)
+        else {
+            panic!("series layer missing");
+        };
+        assert_eq!(values, &vec![2.0, 3.0]);
+    }
+
+    #[test]
+    fn advance_slideshow_holds_each_image_for_interval() {
+        let now = Instant::now();
+        let interval = Duration::from_secs(5);
+        let mut anim = AnimState {
+            ticks: 0,
+            next_update: now,
+        };
+        assert_eq!(advance_slideshow_state(&mut anim, 2, interval, now), Some(0));
+        assert_eq!(
+            advance_slideshow_state(&mut anim, 2, interval, now + Duration::from_secs(1)),
+            Some(0)
+        );
+        assert_eq!(advance_slideshow_state(&mut anim, 2, interval, now + interval), Some(1));
+        // Falling far behind advances only a single image
+        assert_eq!(
+            advance_slideshow_state(&mut anim, 2, interval, now + Duration::from_secs(60)),
+            Some(0)
+        );
+        assert_eq!(advance_slideshow_state(&mut anim, 0, interval, now), None);
+    }
+
+    #[test]
+    fn blit_subpixel_dithers_partial_columns() {
+        let block = Bitmap::new(4, 4, true);
+        let mut whole = Bitmap::new(8, 4, false);
+        blit_subpixel(&mut whole, &block, 2.0, 0);
+        let mut expected = Bitmap::new(8, 4, false);
+        expected.blit(&block, 2, 0, false);
+        assert!(whole == expected);
+
+        let mut half = Bitmap::new(8, 4, false);
+        blit_subpixel(&mut half, &block, 2.5, 0);
+        let column = |x: usize| (0..4).filter(|y| half.data[x + y * 8]).count();
+        assert_eq!(
+            [column(1), column(2), column(3), column(4), column(5), column(6)],
+            [0, 2, 4, 4, 4, 2]
+        );
+    }
+
+    #[test]
+    fn two_color_images_ignore_threshold() {
+        let gray = image::Rgba([40, 40, 40, 255]);
+        let black = image::Rgba([0, 0, 0, 255]);
+        let img = image::RgbaImage::from_fn(2, 1, |x, _| if x == 0 { gray } else { black });
+        let bitmap = bitmap_from_image(&img, 100);
+        assert_eq!(bitmap.data.iter().collect::<Vec<_>>(), vec![true, false]);
+
+        let img = image::RgbaImage::from_fn(3, 1, |x, _| image::Rgba([x as u8 * 100; 4]));
+        let bitmap = bitmap_from_image(&img, 100);
+        assert_eq!(bitmap.data.iter().collect::<Vec<_>>(), vec![false, true, true]);
+    }
+
+    #[test]
+    fn gray_mode_dithers_all_but_two_color_images() {
+        let gray = image::RgbaImage::from_fn(4, 4, |x, _| {
+            image::Rgba([100 + x as u8, 100 + x as u8, 100 + x as u8, 255])
+        });
+        let dithered = bitmap_from_image_with_mode(&gray, GrayMode::Bayer4);
+        let lit = dithered.data.iter().filter(|on| *on).count();
+        assert!(lit > 0 && lit < 16);
+        assert!(bitmap_from_image(&gray, 0x80).data.none());
+
+        let gray = image::Rgba([40, 40, 40, 255]);
+        let black = image::Rgba([0, 0, 0, 255]);
+        let two_color = image::RgbaImage::from_fn(2, 1, |x, _| if x == 0 { gray } else { black });
+        let bitmap = bitmap_from_image_with_mode(&two_color, GrayMode::Bayer4);
+        assert_eq!(bitmap.data.iter().collect::<Vec<_>>(), vec![true, false]);
+    }
+
+    #[test]
+    fn typewriter_reveals_one_character_per_frame() {
+        let texter = TextRenderer::new_merged();
+        let delay = Duration::from_millis(80);
+        let frames = texter.render_typewriter("Hi\nyo", delay);
+        assert_eq!(frames.len(), 4);
+        assert!(frames.iter().all(|frame| frame.delay == Some(delay)));
+        let lit = |bitmap: &Bitmap| bitmap.data.iter().filter(|on| *on).count();
+        for pair in frames.windows(2) {
+            assert_eq!(
+                (pair[0].bitmap.w, pair[0].bitmap.h),
+                (pair[1].bitmap.w, pair[1].bitmap.h)
+            );
+            assert!(lit(&pair[0].bitmap) < lit(&pair[1].bitmap));
+        }
+        let lines = texter.render_lines("Hi\nyo");
+        let mut full = Bitmap::new(frames[3].bitmap.w, frames[3].bitmap.h, false);
+        for (i, line) in lines.iter().enumerate() {
+            full.blit(line, 0, (i * texter.line_height()) as isize, false);
+        }
+        assert!(*frames[3].bitmap == full);
+        assert!(texter.render_typewriter("", delay).is_empty());
+    }
+
+    #[test]
+    fn cycle_shift_scales_steps_by_amplitude() {
+        assert_eq!(cycle_shift(0, 3), (0, 0));
+        assert_eq!(cycle_shift(2, 1), (1, -1));
+        assert_eq!(cycle_shift(2, 3), (3, -3));
+        assert_eq!(cycle_shift(OLED_SHIFTS.len() + 7, 2), (-2, 0));
+    }
+
+    #[test]
+    fn jitter_shift_is_seeded_and_stays_within_radius() {
+        let offsets = |seed| {
+            let mut state = seed;
+            let mut shift = (0, 0);
+            (0..50)
+                .map(|_| {
+                    shift = next_jitter_shift(&mut state, 2, shift);
+                    shift
+                })
+                .collect::<Vec<_>>()
+        };
+        let first = offsets(7);
+        assert_eq!(first, offsets(7));
+        assert_ne!(first, offsets(8));
+        assert!(first.iter().all(|(x, y)| x.abs() <= 2 && y.abs() <= 2));
+        assert!(first.windows(2).all(|pair| pair[0] != pair[1]));
+        assert_eq!(next_jitter_shift(&mut 7, 0, (1, 1)), (0, 0));
+    }
+
+    #[test]
+    fn bitmap_to_png_round_trips() {
+        let mut bitmap = Bitmap::new(3, 2, false);
+        bitmap.data.set(1, true);
+        bitmap.data.set(5, true);
+        let png = bitmap_to_png(&bitmap).unwrap();
+        assert!(bitmap_from_memory(&png, 0x80).unwrap() == bitmap);
+    }
+
+    #[test]
+    fn bitmap_to_png_scaled_enlarges_pixels() {
+        let mut bitmap = Bitmap::new(3, 2, false);
+        bitmap.data.set(1, true);
+        let png = bitmap_to_png_scaled(&bitmap, 4).unwrap();
+        assert!(bitmap_from_memory(&png, 0x80).unwrap() == bitmap.scale(4));
+        let png = bitmap_to_png_scaled(&bitmap, 0).unwrap();
+        assert!(bitmap_from_memory(&png, 0x80).unwrap() == bitmap);
+    }
+
+    #[test]
+    fn render_transition_moves_from_old_to_new() {
+        let from = Bitmap::new(8, 4, true);
+        let to = Bitmap::new(8, 4, false);
+        for kind in [TransitionKind::Wipe, TransitionKind::Slide, TransitionKind::Dissolve] {
+            assert!(render_transition(&from, &to, kind, 0, 4) == from);
+            assert!(render_transition(&from, &to, kind, 4, 4) == to);
+            let half = render_transition(&from, &to, kind, 2, 4);
+            assert_eq!(half.data.iter().filter(|on| *on).count(), 16);
+        }
+    }
+
+    #[test]
+    fn set_layer_bitmap_only_transitions_when_configured() {
+        let mut layers = LayerMap::new();
+        let mut counter = 0;
+        let old = Arc::new(Bitmap::new(2, 2, false));
+        let new = Arc::new(Bitmap::new(2, 2, true));
+        let id = add_layer_to_map(
+            &mut counter,
+            &mut layers,
+            DrawLayer::Image {
+                bitmap: old.clone(),
+                x: 0,
+                y: 0,
+            },
+        );
+        set_layer_bitmap(&mut layers, id, new.clone());
+        assert!(layers[&id].transition.from.is_none());
+
+        set_layer_bitmap(&mut layers, id, old.clone());
+        let config = TransitionConfig {
+            kind: TransitionKind::Wipe,
+            frames: 2,
+        };
+        set_layer_transition(&mut layers, id, Some(config));
+        set_layer_bitmap(&mut layers, id, new.clone());
+        let state = layers.get_mut(&id).unwrap();
+        assert!(state.transition.from.is_some());
+        let DrawLayer::Image { bitmap, .. } = &state.layer else {
+            panic!("image layer missing");
+        };
+        let bitmap = bitmap.clone();
+        assert!(*advance_transition(&mut state.transition, &bitmap) != *new);
+        assert!(*advance_transition(&mut state.transition, &bitmap) == *new);
+        assert!(state.transition.from.is_none());
+    }
+
+    #[test]
+    fn morph_layers_transitions_kept_layers_to_the_new_position() {
+        let mut layers = LayerMap::new();
+        let mut counter = 0;
+        let image = |w, x| DrawLayer::Image {
+            bitmap: Arc::new(Bitmap::new(w, 2, true)),
+            x,
+            y: 0,
+        };
+        let kept = add_layer_to_map(&mut counter, &mut layers, image(2, 10));
+        let removed = add_layer_to_map(&mut counter, &mut layers, image(2, 10));
+        set_layer_transition(
+            &mut layers,
+            kept,
+            Some(TransitionConfig {
+                kind: TransitionKind::Dissolve,
+                frames: 4,
+            }),
+        );
+        let moved = add_layer_to_map(&mut counter, &mut layers, image(4, 6));
+        let added = add_layer_to_map(&mut counter, &mut layers, image(4, 6));
+        assert_eq!(
+            morph_layers(&mut layers, &[kept, removed], vec![moved, added]),
+            vec![kept, added]
+        );
+        assert!(!layers.contains_key(&removed) && !layers.contains_key(&moved));
+        let state = layers.get_mut(&kept).unwrap();
+        let DrawLayer::Image { bitmap, x, .. } = &state.layer else {
+            panic!("image layer missing");
+        };
+        assert_eq!((*x, bitmap.w), (6, 4));
+        let bitmap = bitmap.clone();
+        assert_eq!(state.transition.from_offset, (4, 0));
+        // The old bitmap ended at x = 12, so the transition covers 6 pixels from the new position
+        assert_eq!(advance_transition(&mut state.transition, &bitmap).w, 6);
+        assert_eq!(transition_origin(&state.transition), (0, 0));
+    }
 }
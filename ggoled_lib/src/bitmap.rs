@@ -1,8 +1,34 @@
 #![allow(dead_code)]
 
+use anyhow::{bail, Context};
 pub use bit_vec::BitVec;
 
-#[derive(PartialEq)]
+/// How `Bitmap::blit_gray` turns grayscale levels into pixels.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum GrayMode {
+    /// Levels above the given one are set.
+    Threshold(u8),
+    /// Ordered dithering with a 2x2 Bayer matrix, giving 5 shades.
+    Bayer2,
+    /// Ordered dithering with a 4x4 Bayer matrix, giving 17 shades.
+    Bayer4,
+}
+impl GrayMode {
+    // Level that a grayscale value at screen position `x`,`y` has to exceed to be set
+    fn level(&self, x: usize, y: usize) -> usize {
+        match self {
+            GrayMode::Threshold(level) => *level as usize,
+            GrayMode::Bayer2 => BAYER_2X2[y % 2][x % 2] * 64 + 32,
+            GrayMode::Bayer4 => BAYER_4X4[y % 4][x % 4] * 16 + 8,
+        }
+    }
+}
+
+const BAYER_2X2: [[usize; 2]; 2] = [[0, 2], [3, 1]];
+/// 4x4 Bayer matrix for ordered dithering, with thresholds 0-15 indexed by `[y % 4][x % 4]`.
+pub const BAYER_4X4: [[usize; 4]; 4] = [[0, 8, 2, 10], [12, 4, 14, 6], [3, 11, 1, 9], [15, 7, 13, 5]];
+
+#[derive(Clone, Debug, PartialEq)]
 pub struct Bitmap {
     pub w: usize,
     pub h: usize,
@@ -14,32 +40,74 @@ impl Bitmap {
         Bitmap { w, h, data }
     }
 
-    /// Crop Bitmap to a new size. Out of bounds positions and sizes will panic.
+    /// Create a Bitmap from rows of text, where `on_char` is a set pixel and any other character is unset.
+    /// The width is that of the longest row, shorter rows are padded with unset pixels.
+    pub fn from_ascii(rows: &[&str], on_char: char) -> Self {
+        let w = rows.iter().map(|row| row.chars().count()).max().unwrap_or(0);
+        let mut bitmap = Bitmap::new(w, rows.len(), false);
+        for (y, row) in rows.iter().enumerate() {
+            for (x, ch) in row.chars().enumerate() {
+                bitmap.data.set(x + y * w, ch == on_char);
+            }
+        }
+        bitmap
+    }
+
+    /// Crop Bitmap to a new size. Out of bounds positions and sizes will panic, see `try_crop` otherwise.
     pub fn crop(&self, x: usize, y: usize, w: usize, h: usize) -> Self {
-        assert!(x <= self.w && y <= self.h);
-        assert!(w <= self.w - x && h <= self.h - y);
+        self.try_crop(x, y, w, h).unwrap()
+    }
+
+    /// Crop Bitmap to a new size, failing if the area isn't within bounds.
+    pub fn try_crop(&self, x: usize, y: usize, w: usize, h: usize) -> anyhow::Result<Self> {
+        if x > self.w || y > self.h || w > self.w - x || h > self.h - y {
+            bail!(
+                "crop {}x{} at ({}, {}) is out of bounds for a {}x{} bitmap",
+                w,
+                h,
+                x,
+                y,
+                self.w,
+                self.h
+            );
+        }
         let mut data = BitVec::with_capacity(w * h);
-        for y in 0..h {
-            for x in 0..w {
-                data.push(self.data[x + y * self.w]);
+        for cy in 0..h {
+            for cx in 0..w {
+                data.push(self.data[(x + cx) + (y + cy) * self.w]);
             }
         }
-        Self { w, h, data }
+        Ok(Self { w, h, data })
     }
 
     /// Blit another Bitmap onto this one. Bounds will *not* be expanded.
     /// `opaque=true` means all pixels will be blitted. `opaque=false` means only set pixels will be blitted (i.e. unset pixels act as if transparent).
     pub fn blit(&mut self, other: &Bitmap, x: isize, y: isize, opaque: bool) {
+        self.blit_region(other, (0, 0, other.w, other.h), x, y, opaque);
+    }
+
+    /// Blit the `(x, y, w, h)` area `region` of another Bitmap onto this one, with its top left corner at `x`,`y`.
+    /// The region is clipped to the bounds of `other`. `opaque` works the same as for `blit`.
+    pub fn blit_region(
+        &mut self,
+        other: &Bitmap,
+        region: (usize, usize, usize, usize),
+        x: isize,
+        y: isize,
+        opaque: bool,
+    ) {
+        let region_x = region.0.min(other.w);
+        let region_y = region.1.min(other.h);
+        let region_w = region.2.min(other.w - region_x);
+        let region_h = region.3.min(other.h - region_y);
         let src_x_start = (-x).max(0) as usize;
         let src_y_start = (-y).max(0) as usize;
         let dst_x_start = x.max(0) as usize;
         let dst_y_start = y.max(0) as usize;
-        let overlap_w = other
-            .w
+        let overlap_w = region_w
             .saturating_sub(src_x_start)
             .min(self.w.saturating_sub(dst_x_start));
-        let overlap_h = other
-            .h
+        let overlap_h = region_h
             .saturating_sub(src_y_start)
             .min(self.h.saturating_sub(dst_y_start));
         if overlap_w == 0 || overlap_h == 0 {
@@ -51,7 +119,7 @@ impl Bitmap {
             for ox in src_x_start..(src_x_start + overlap_w) {
                 let sx = dst_x_start + (ox - src_x_start);
                 let si = sx + sy * self.w;
-                let oi = ox + oy * other.w;
+                let oi = (region_x + ox) + (region_y + oy) * other.w;
                 if opaque {
                     self.data.set(si, other.data[oi]);
                 } else {
@@ -61,27 +129,413 @@ impl Bitmap {
         }
     }
 
+    /// Blit a grayscale image onto this one, `src` holding `w`x`h` levels row by row with 0 as transparent.
+    /// Levels are turned into set pixels according to `mode`, while unset ones leave the bitmap as it is, like
+    /// `blit` with `opaque=false`. Dither patterns line up with this bitmap, so neighbouring blits tile seamlessly.
+    /// Bounds will *not* be expanded and levels missing from `src` count as 0.
+    pub fn blit_gray(&mut self, src: &[u8], w: usize, h: usize, x: isize, y: isize, mode: GrayMode) {
+        for sy in 0..h {
+            let dy = y + sy as isize;
+            if dy < 0 || dy as usize >= self.h {
+                continue;
+            }
+            for sx in 0..w {
+                let dx = x + sx as isize;
+                if dx < 0 || dx as usize >= self.w {
+                    continue;
+                }
+                let (dx, dy) = (dx as usize, dy as usize);
+                let value = src.get(sx + sy * w).copied().unwrap_or(0) as usize;
+                if value > mode.level(dx, dy) {
+                    self.data.set(dx + dy * self.w, true);
+                }
+            }
+        }
+    }
+
+    /// Render the bitmap as text, one line per row with `#` for on pixels and spaces for off pixels.
+    pub fn to_ascii(&self) -> String {
+        let rows: Vec<String> = (0..self.h)
+            .map(|y| {
+                (0..self.w)
+                    .map(|x| if self.data[x + y * self.w] { '#' } else { ' ' })
+                    .collect()
+            })
+            .collect();
+        rows.join("\n")
+    }
+
+    /// Read a plain (`P1`) or binary (`P4`) PBM image, where `1` is a set pixel.
+    pub fn from_pbm(bytes: &[u8]) -> anyhow::Result<Self> {
+        let mut pos = 0;
+        let binary = match next_pbm_token(bytes, &mut pos) {
+            Some(b"P1") => false,
+            Some(b"P4") => true,
+            _ => bail!("not a PBM image"),
+        };
+        let w = pbm_dimension(bytes, &mut pos)?;
+        let h = pbm_dimension(bytes, &mut pos)?;
+        let len = w.checked_mul(h).context("PBM image is too large")?;
+        // A single whitespace byte separates a binary header from the packed rows
+        let data = if binary {
+            bytes.get(pos + 1..).unwrap_or_default()
+        } else {
+            &bytes[pos..]
+        };
+        let row_bytes = w.div_ceil(8);
+        // Checked before allocating so a bogus header can't ask for a huge bitmap. Plain pixels take at least a byte each
+        let min_len = if binary {
+            row_bytes.checked_mul(h).context("PBM image is too large")?
+        } else {
+            len
+        };
+        if data.len() < min_len {
+            bail!("PBM image data is truncated");
+        }
+        let mut bitmap = Bitmap::new(w, h, false);
+        if binary {
+            for y in 0..h {
+                for x in 0..w {
+                    let byte = data[y * row_bytes + x / 8];
+                    bitmap.data.set(x + y * w, byte & (0x80 >> (x % 8)) != 0);
+                }
+            }
+        } else {
+            let mut i = 0;
+            for &byte in data {
+                if i == len {
+                    break;
+                }
+                match byte {
+                    b'0' | b'1' => {
+                        bitmap.data.set(i, byte == b'1');
+                        i += 1;
+                    }
+                    byte if byte.is_ascii_whitespace() => {}
+                    _ => bail!("unexpected {:?} in PBM image data", byte as char),
+                }
+            }
+            if i < len {
+                bail!("PBM image data is truncated");
+            }
+        }
+        Ok(bitmap)
+    }
+
+    /// Encode the bitmap as a PBM image, `P4` if `binary` and `P1` otherwise. Set pixels are `1`.
+    pub fn to_pbm(&self, binary: bool) -> Vec<u8> {
+        let mut out = format!("{}\n{} {}\n", if binary { "P4" } else { "P1" }, self.w, self.h).into_bytes();
+        if binary {
+            out.extend(self.packed_rows(true));
+        } else {
+            // Lines of plain PBM shouldn't be longer than 70 characters
+            for y in 0..self.h {
+                let row: Vec<u8> = (0..self.w)
+                    .map(|x| if self.data[x + y * self.w] { b'1' } else { b'0' })
+                    .collect();
+                for line in row.chunks(70) {
+                    out.extend_from_slice(line);
+                    out.push(b'\n');
+                }
+            }
+        }
+        out
+    }
+
+    /// Read an XBM image, i.e. the C source with `_width` and `_height` defines followed by the pixel bytes.
+    pub fn from_xbm(text: &str) -> anyhow::Result<Self> {
+        let (mut w, mut h) = (None, None);
+        for line in text.lines() {
+            let mut parts = line.split_whitespace();
+            if parts.next() != Some("#define") {
+                continue;
+            }
+            let (Some(name), Some(value)) = (parts.next(), parts.next()) else {
+                continue;
+            };
+            if name.ends_with("_width") {
+                w = Some(value.parse::<usize>().context("invalid XBM width")?);
+            } else if name.ends_with("_height") {
+                h = Some(value.parse::<usize>().context("invalid XBM height")?);
+            }
+        }
+        let (Some(w), Some(h)) = (w, h) else {
+            bail!("XBM image is missing its width or height");
+        };
+        let start = text.find('{').context("XBM image has no pixel data")?;
+        let end = start + text[start..].find('}').context("XBM pixel data isn't closed")?;
+        let data = text[start + 1..end]
+            .split(',')
+            .map(str::trim)
+            .filter(|value| !value.is_empty())
+            .map(
+                |value| match value.strip_prefix("0x").or_else(|| value.strip_prefix("0X")) {
+                    Some(hex) => u8::from_str_radix(hex, 16),
+                    None => value.parse(),
+                },
+            )
+            .collect::<Result<Vec<u8>, _>>()
+            .context("invalid XBM pixel data")?;
+        let row_bytes = w.div_ceil(8);
+        if data.len() < row_bytes.checked_mul(h).context("XBM image is too large")? {
+            bail!("XBM image data is truncated");
+        }
+        let mut bitmap = Bitmap::new(w, h, false);
+        for y in 0..h {
+            for x in 0..w {
+                let byte = data[y * row_bytes + x / 8];
+                bitmap.data.set(x + y * w, byte & (1 << (x % 8)) != 0);
+            }
+        }
+        Ok(bitmap)
+    }
+
+    /// Encode the bitmap as an XBM image, using `name` as the prefix of its C identifiers.
+    pub fn to_xbm(&self, name: &str) -> String {
+        let bytes: Vec<String> = self
+            .packed_rows(false)
+            .iter()
+            .map(|byte| format!("0x{byte:02x}"))
+            .collect();
+        let lines: Vec<String> = bytes.chunks(12).map(|line| format!("   {}", line.join(", "))).collect();
+        format!(
+            "#define {name}_width {}\n#define {name}_height {}\nstatic unsigned char {name}_bits[] = {{\n{} }};\n",
+            self.w,
+            self.h,
+            lines.join(",\n")
+        )
+    }
+
+    // Packs each row into bytes, padded at the end. PBM puts the leftmost pixel in the highest bit, XBM in the lowest.
+    fn packed_rows(&self, msb_first: bool) -> Vec<u8> {
+        let row_bytes = self.w.div_ceil(8);
+        let mut out = vec![0; row_bytes * self.h];
+        for y in 0..self.h {
+            for x in 0..self.w {
+                if self.data[x + y * self.w] {
+                    out[y * row_bytes + x / 8] |= if msb_first { 0x80 >> (x % 8) } else { 1 << (x % 8) };
+                }
+            }
+        }
+        out
+    }
+
+    /// Enlarges the bitmap by an integer `factor` in both directions, turning each pixel into a square block.
+    pub fn scale(&self, factor: usize) -> Self {
+        let mut out = Bitmap::new(self.w * factor, self.h * factor, false);
+        for y in 0..out.h {
+            for x in 0..out.w {
+                out.data.set(x + y * out.w, self.data[x / factor + y / factor * self.w]);
+            }
+        }
+        out
+    }
+
+    /// Iterate over the `(x, y)` positions of all set pixels, row by row.
+    /// Whole blocks of unset pixels are skipped at once, so this is cheap on mostly empty bitmaps.
+    pub fn set_pixels(&self) -> impl Iterator<Item = (usize, usize)> + '_ {
+        // `BitVec` keeps bit `i` at bit `i % 32` of block `i / 32`, with any bits past the end unset
+        self.data.blocks().enumerate().flat_map(move |(block_i, mut block)| {
+            std::iter::from_fn(move || {
+                if block == 0 {
+                    return None;
+                }
+                let i = block_i * 32 + block.trailing_zeros() as usize;
+                block &= block - 1;
+                Some((i % self.w, i / self.w))
+            })
+        })
+    }
+
     /// Inverts all pixels in the bitmap.
     pub fn invert(&mut self) {
         self.data.negate();
     }
+
+    // Sets a single pixel, ignoring positions outside of the bitmap.
+    fn plot(&mut self, x: isize, y: isize, on: bool) {
+        if x >= 0 && y >= 0 && (x as usize) < self.w && (y as usize) < self.h {
+            self.data.set(x as usize + y as usize * self.w, on);
+        }
+    }
+
+    // Sets a horizontal run of pixels from `x0` to `x1` (inclusive), clipped to bounds.
+    fn plot_span(&mut self, x0: isize, x1: isize, y: isize, on: bool) {
+        if y < 0 || y as usize >= self.h {
+            return;
+        }
+        let start = x0.min(x1).max(0) as usize;
+        let end = (x0.max(x1) + 1).clamp(0, self.w as isize) as usize;
+        for x in start..end {
+            self.data.set(x + y as usize * self.w, on);
+        }
+    }
+
+    /// Draw a line between two points (both inclusive) using Bresenham's algorithm. Clipped to bounds.
+    pub fn draw_line(&mut self, x0: isize, y0: isize, x1: isize, y1: isize, on: bool) {
+        let dx = (x1 - x0).abs();
+        let dy = -(y1 - y0).abs();
+        let sx = if x0 < x1 { 1 } else { -1 };
+        let sy = if y0 < y1 { 1 } else { -1 };
+        let mut err = dx + dy;
+        let (mut x, mut y) = (x0, y0);
+        loop {
+            self.plot(x, y, on);
+            if x == x1 && y == y1 {
+                break;
+            }
+            let e2 = 2 * err;
+            if e2 >= dy {
+                err += dy;
+                x += sx;
+            }
+            if e2 <= dx {
+                err += dx;
+                y += sy;
+            }
+        }
+    }
+
+    /// Draw a rectangle outline with the top-left corner at `x`,`y`. Clipped to bounds.
+    pub fn draw_rect(&mut self, x: isize, y: isize, w: usize, h: usize, on: bool) {
+        if w == 0 || h == 0 {
+            return;
+        }
+        let (x1, y1) = (x + w as isize - 1, y + h as isize - 1);
+        self.plot_span(x, x1, y, on);
+        self.plot_span(x, x1, y1, on);
+        for py in y..=y1 {
+            self.plot(x, py, on);
+            self.plot(x1, py, on);
+        }
+    }
+
+    /// Fill a rectangle with the top-left corner at `x`,`y`. Clipped to bounds.
+    pub fn fill_rect(&mut self, x: isize, y: isize, w: usize, h: usize, on: bool) {
+        if w == 0 {
+            return;
+        }
+        let y_start = y.max(0);
+        let y_end = (y + h as isize).min(self.h as isize);
+        for py in y_start..y_end {
+            self.plot_span(x, x + w as isize - 1, py, on);
+        }
+    }
+
+    /// Draw a circle outline centered on `cx`,`cy` using the midpoint algorithm. Clipped to bounds.
+    pub fn draw_circle(&mut self, cx: isize, cy: isize, r: usize, on: bool) {
+        let r = r as isize;
+        let (mut x, mut y, mut err) = (r, 0, 1 - r);
+        while x >= y {
+            for (px, py) in [(x, y), (y, x), (-y, x), (-x, y), (-x, -y), (-y, -x), (y, -x), (x, -y)] {
+                self.plot(cx + px, cy + py, on);
+            }
+            y += 1;
+            if err < 0 {
+                err += 2 * y + 1;
+            } else {
+                x -= 1;
+                err += 2 * (y - x) + 1;
+            }
+        }
+    }
+
+    /// Fill a circle centered on `cx`,`cy` using the midpoint algorithm. Clipped to bounds.
+    pub fn fill_circle(&mut self, cx: isize, cy: isize, r: usize, on: bool) {
+        let r = r as isize;
+        let (mut x, mut y, mut err) = (r, 0, 1 - r);
+        while x >= y {
+            self.plot_span(cx - x, cx + x, cy + y, on);
+            self.plot_span(cx - x, cx + x, cy - y, on);
+            self.plot_span(cx - y, cx + y, cy + x, on);
+            self.plot_span(cx - y, cx + y, cy - x, on);
+            y += 1;
+            if err < 0 {
+                err += 2 * y + 1;
+            } else {
+                x -= 1;
+                err += 2 * (y - x) + 1;
+            }
+        }
+    }
+
+    /// Draw `values` as a sparkline within the given rectangle, scaled so the series spans its full height.
+    /// Each value takes one column and the series is right-aligned, so only the last `w` values are shown.
+    /// Non-finite values leave a gap, and a constant series is drawn through the middle of the rectangle.
+    pub fn plot_series(&mut self, values: &[f32], x: isize, y: isize, w: usize, h: usize) {
+        if w == 0 || h == 0 {
+            return;
+        }
+        let values = &values[values.len().saturating_sub(w)..];
+        let (min, max) = values
+            .iter()
+            .filter(|v| v.is_finite())
+            .fold((f32::INFINITY, f32::NEG_INFINITY), |(min, max), &v| {
+                (min.min(v), max.max(v))
+            });
+        if min > max {
+            return; // no finite values
+        }
+        let x_start = x + (w - values.len()) as isize;
+        let bottom = y + h as isize - 1;
+        let mut prev = None;
+        for (i, &v) in values.iter().enumerate() {
+            if !v.is_finite() {
+                prev = None;
+                continue;
+            }
+            let px = x_start + i as isize;
+            let py = if max > min {
+                bottom - ((v - min) / (max - min) * (h - 1) as f32).round() as isize
+            } else {
+                y + (h as isize - 1) / 2
+            };
+            match prev {
+                Some((prev_x, prev_y)) => self.draw_line(prev_x, prev_y, px, py, true),
+                None => self.plot(px, py, true),
+            }
+            prev = Some((px, py));
+        }
+    }
+}
+
+// Reads the next whitespace separated token of a PBM header, skipping `#` comments
+fn next_pbm_token<'a>(bytes: &'a [u8], pos: &mut usize) -> Option<&'a [u8]> {
+    loop {
+        while bytes.get(*pos).is_some_and(u8::is_ascii_whitespace) {
+            *pos += 1;
+        }
+        if bytes.get(*pos) != Some(&b'#') {
+            break;
+        }
+        while bytes.get(*pos).is_some_and(|byte| *byte != b'\n') {
+            *pos += 1;
+        }
+    }
+    let start = *pos;
+    while bytes
+        .get(*pos)
+        .is_some_and(|byte| !byte.is_ascii_whitespace() && *byte != b'#')
+    {
+        *pos += 1;
+    }
+    (*pos > start).then(|| &bytes[start..*pos])
+}
+
+fn pbm_dimension(bytes: &[u8], pos: &mut usize) -> anyhow::Result<usize> {
+    let token = next_pbm_token(bytes, pos).context("PBM header is truncated")?;
+    std::str::from_utf8(token)
+        .ok()
+        .and_then(|token| token.parse().ok())
+        .context("invalid PBM image size")
 }
 
 #[cfg(test)]
 mod tests {
-    use super::Bitmap;
+    use super::{Bitmap, GrayMode};
 
     fn bitmap_from_rows(rows: &[&str]) -> Bitmap {
-        let h = rows.len();
-        let w = rows.first().map_or(0, |r| r.len());
-        let mut bitmap = Bitmap::new(w, h, false);
-        for (y, row) in rows.iter().enumerate() {
-            assert_eq!(row.len(), w);
-            for (x, ch) in row.chars().enumerate() {
-                bitmap.data.set(y * w + x, ch == '1');
-            }
-        }
-        bitmap
+        Bitmap::from_ascii(rows, '1')
     }
 
     fn rows_from_bitmap(bitmap: &Bitmap) -> Vec<String> {
@@ -96,6 +550,104 @@ mod tests {
         rows
     }
 
+    #[test]
+    fn from_ascii_pads_short_rows() {
+        let bitmap = Bitmap::from_ascii(&["#.#", "#", ""], '#');
+        assert_eq!((bitmap.w, bitmap.h), (3, 3));
+        assert_eq!(rows_from_bitmap(&bitmap), vec!["101", "100", "000"]);
+        assert_eq!(Bitmap::from_ascii(&[], '#').data.len(), 0);
+    }
+
+    #[test]
+    fn to_ascii_round_trips_through_from_ascii() {
+        let bitmap = bitmap_from_rows(&["101", "100"]);
+        let ascii = bitmap.to_ascii();
+        assert_eq!(ascii, "# #\n#  ");
+        assert_eq!(Bitmap::from_ascii(&ascii.lines().collect::<Vec<_>>(), '#'), bitmap);
+        assert_eq!(Bitmap::new(0, 0, false).to_ascii(), "");
+    }
+
+    #[test]
+    fn blit_gray_thresholds_and_dithers() {
+        let mut bitmap = Bitmap::new(3, 2, false);
+        bitmap.blit_gray(&[0, 128, 255, 100, 200, 50], 3, 2, 0, 0, GrayMode::Threshold(127));
+        assert_eq!(rows_from_bitmap(&bitmap), vec!["011", "010"]);
+
+        // Half gray sets half of each matrix, in the same places whatever the blit offset
+        let half = [128; 16];
+        let mut bitmap = Bitmap::new(4, 4, false);
+        bitmap.blit_gray(&half, 4, 4, 0, 0, GrayMode::Bayer2);
+        assert_eq!(rows_from_bitmap(&bitmap), vec!["1010", "0101", "1010", "0101"]);
+        let mut bitmap = Bitmap::new(4, 4, false);
+        bitmap.blit_gray(&half, 4, 4, 0, 0, GrayMode::Bayer4);
+        assert_eq!(bitmap.data.iter().filter(|on| *on).count(), 8);
+        let mut shifted = Bitmap::new(4, 4, false);
+        shifted.blit_gray(&[128; 4], 2, 2, 2, 1, GrayMode::Bayer4);
+        let mut expected = Bitmap::new(4, 4, false);
+        expected.blit_region(&bitmap, (2, 1, 2, 2), 2, 1, true);
+        assert_eq!(shifted, expected);
+
+        // Blank and full levels stay fully unset or set, and unset pixels don't clear the bitmap
+        let mut bitmap = Bitmap::new(2, 2, true);
+        bitmap.blit_gray(&[0; 4], 2, 2, 0, 0, GrayMode::Bayer4);
+        assert!(bitmap.data.all());
+        let mut bitmap = Bitmap::new(4, 4, false);
+        bitmap.blit_gray(&[255; 16], 4, 4, 0, 0, GrayMode::Bayer4);
+        assert!(bitmap.data.all());
+    }
+
+    #[test]
+    fn set_pixels_yields_positions_row_by_row() {
+        let bitmap = bitmap_from_rows(&["0100", "0000", "1001"]);
+        assert_eq!(bitmap.set_pixels().collect::<Vec<_>>(), vec![(1, 0), (0, 2), (3, 2)]);
+        assert_eq!(Bitmap::new(0, 0, false).set_pixels().count(), 0);
+        assert_eq!(Bitmap::new(3, 2, true).set_pixels().count(), 6);
+        let mut wide = Bitmap::new(40, 2, false);
+        wide.invert();
+        assert_eq!(wide.set_pixels().count(), 80);
+        assert_eq!(wide.set_pixels().last(), Some((39, 1)));
+    }
+
+    #[test]
+    fn scale_repeats_pixels_in_blocks() {
+        let bitmap = bitmap_from_rows(&["10", "01"]);
+        assert_eq!(rows_from_bitmap(&bitmap.scale(1)), vec!["10", "01"]);
+        assert_eq!(rows_from_bitmap(&bitmap.scale(2)), vec!["1100", "1100", "0011", "0011"]);
+        assert_eq!(bitmap.scale(0).data.len(), 0);
+    }
+
+    #[test]
+    fn try_crop_copies_offset_region() {
+        let bitmap = bitmap_from_rows(&["1000", "0110", "0010"]);
+        let cropped = bitmap.try_crop(1, 1, 2, 2).unwrap();
+        assert_eq!(rows_from_bitmap(&cropped), vec!["11", "01"]);
+        assert_eq!(bitmap.try_crop(4, 3, 0, 0).unwrap().data.len(), 0);
+    }
+
+    #[test]
+    fn try_crop_rejects_out_of_bounds() {
+        let bitmap = Bitmap::new(4, 3, true);
+        assert!(bitmap.try_crop(5, 0, 0, 0).is_err());
+        assert!(bitmap.try_crop(0, 4, 0, 0).is_err());
+        assert!(bitmap.try_crop(2, 0, 3, 1).is_err());
+        assert!(bitmap.try_crop(0, 1, 1, 3).is_err());
+    }
+
+    #[test]
+    fn blit_region_copies_only_the_region() {
+        let src = bitmap_from_rows(&["1100", "0110", "0011"]);
+        let mut dst = Bitmap::new(3, 3, false);
+        dst.blit_region(&src, (1, 1, 2, 2), 1, 0, true);
+        assert_eq!(rows_from_bitmap(&dst), vec!["011", "001", "000"]);
+
+        // Regions reaching past the source are clipped to it
+        let mut dst = Bitmap::new(3, 2, false);
+        dst.blit_region(&src, (2, 2, 10, 10), -1, 0, true);
+        assert_eq!(rows_from_bitmap(&dst), vec!["100", "000"]);
+        dst.blit_region(&src, (9, 9, 1, 1), 0, 0, true);
+        assert_eq!(rows_from_bitmap(&dst), vec!["100", "000"]);
+    }
+
     #[test]
     fn blit_opaque_overwrites_destination() {
         let mut dst = bitmap_from_rows(&["111", "111", "111"]);
@@ -128,4 +680,123 @@ mod tests {
         dst.blit(&src, 10, 10, true);
         assert_eq!(rows_from_bitmap(&dst), before);
     }
+
+    #[test]
+    fn draw_rect_outlines_3x3() {
+        let mut bitmap = Bitmap::new(5, 5, false);
+        bitmap.draw_rect(1, 1, 3, 3, true);
+        assert_eq!(
+            rows_from_bitmap(&bitmap),
+            vec!["00000", "01110", "01010", "01110", "00000"]
+        );
+    }
+
+    #[test]
+    fn fill_rect_clips_to_bounds() {
+        let mut bitmap = Bitmap::new(3, 3, false);
+        bitmap.fill_rect(-1, 1, 3, 5, true);
+        assert_eq!(rows_from_bitmap(&bitmap), vec!["000", "110", "110"]);
+    }
+
+    #[test]
+    fn draw_line_diagonal() {
+        let mut bitmap = Bitmap::new(4, 4, false);
+        bitmap.draw_line(3, 3, 0, 0, true);
+        assert_eq!(rows_from_bitmap(&bitmap), vec!["1000", "0100", "0010", "0001"]);
+    }
+
+    #[test]
+    fn draw_circle_small() {
+        let mut bitmap = Bitmap::new(5, 5, false);
+        bitmap.draw_circle(2, 2, 2, true);
+        assert_eq!(
+            rows_from_bitmap(&bitmap),
+            vec!["01110", "10001", "10001", "10001", "01110"]
+        );
+    }
+
+    #[test]
+    fn fill_circle_small() {
+        let mut bitmap = Bitmap::new(5, 5, false);
+        bitmap.fill_circle(2, 2, 2, true);
+        assert_eq!(
+            rows_from_bitmap(&bitmap),
+            vec!["01110", "11111", "11111", "11111", "01110"]
+        );
+    }
+
+    #[test]
+    fn draw_circle_clips_to_bounds() {
+        let mut bitmap = Bitmap::new(3, 3, false);
+        bitmap.draw_circle(0, 0, 2, true);
+        assert_eq!(rows_from_bitmap(&bitmap), vec!["001", "001", "110"]);
+    }
+
+    #[test]
+    fn plot_series_scales_to_rect() {
+        let mut bitmap = Bitmap::new(3, 3, false);
+        bitmap.plot_series(&[1.0, 2.0, 3.0], 0, 0, 3, 3);
+        assert_eq!(rows_from_bitmap(&bitmap), vec!["001", "010", "100"]);
+    }
+
+    #[test]
+    fn plot_series_keeps_latest_values() {
+        let mut bitmap = Bitmap::new(2, 2, false);
+        bitmap.plot_series(&[5.0, 0.0, 1.0], 0, 0, 2, 2);
+        assert_eq!(rows_from_bitmap(&bitmap), vec!["01", "10"]);
+    }
+
+    #[test]
+    fn plot_series_constant_and_empty() {
+        let mut bitmap = Bitmap::new(4, 3, false);
+        bitmap.plot_series(&[], 0, 0, 4, 3);
+        assert_eq!(rows_from_bitmap(&bitmap), vec!["0000", "0000", "0000"]);
+        bitmap.plot_series(&[7.0, 7.0], 0, 0, 4, 3);
+        assert_eq!(rows_from_bitmap(&bitmap), vec!["0000", "0011", "0000"]);
+    }
+
+    #[test]
+    fn pbm_round_trips_in_both_formats() {
+        let bitmap = bitmap_from_rows(&["1000000001", "0110000110", "0001111000"]);
+        for binary in [false, true] {
+            assert_eq!(Bitmap::from_pbm(&bitmap.to_pbm(binary)).unwrap(), bitmap);
+        }
+        assert_eq!(bitmap.to_pbm(true).len(), "P4\n10 3\n".len() + 2 * 3);
+    }
+
+    #[test]
+    fn from_pbm_skips_comments_and_whitespace() {
+        let bitmap = Bitmap::from_pbm(b"P1\n# a comment\n3 # width\n2\n1 0 1\n0 1 0\n").unwrap();
+        assert_eq!(rows_from_bitmap(&bitmap), vec!["101", "010"]);
+    }
+
+    #[test]
+    fn from_pbm_rejects_bad_input() {
+        assert!(Bitmap::from_pbm(b"P2\n1 1\n0\n").is_err());
+        assert!(Bitmap::from_pbm(b"P1\n2 2\n1 0 1\n").is_err());
+        assert!(Bitmap::from_pbm(b"P1\n1 1\n2\n").is_err());
+        assert!(Bitmap::from_pbm(b"P4\n9 2\n\xff\x80\xff").is_err());
+    }
+
+    #[test]
+    fn from_pbm_rejects_oversized_header_without_allocating() {
+        assert!(Bitmap::from_pbm(b"P4 4000000000 4000000000\n\x00").is_err());
+        assert!(Bitmap::from_pbm(b"P1 4000000000 4000000000\n0").is_err());
+    }
+
+    #[test]
+    fn xbm_round_trips() {
+        let bitmap = bitmap_from_rows(&["1000000001", "0110000110", "0001111000"]);
+        let xbm = bitmap.to_xbm("icon");
+        assert!(xbm.starts_with("#define icon_width 10\n#define icon_height 3\n"));
+        assert_eq!(Bitmap::from_xbm(&xbm).unwrap(), bitmap);
+    }
+
+    #[test]
+    fn from_xbm_reads_lsb_first_bytes() {
+        let xbm = "#define x_width 4\n#define x_height 2\nstatic char x_bits[] = { 0x09, 6 };";
+        assert_eq!(rows_from_bitmap(&Bitmap::from_xbm(xbm).unwrap()), vec!["1001", "0110"]);
+        assert!(Bitmap::from_xbm("#define x_width 4\nstatic char x_bits[] = { 0x09 };").is_err());
+        assert!(Bitmap::from_xbm("#define x_width 4\n#define x_height 2\nstatic char x_bits[] = { 0x09 };").is_err());
+    }
 }
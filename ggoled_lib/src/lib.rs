@@ -2,14 +2,27 @@ pub mod bitmap;
 use anyhow::bail;
 pub use bitmap::Bitmap;
 use hidapi::{HidApi, HidDevice, MAX_REPORT_DESCRIPTOR_SIZE};
-use std::{cmp::min, time::Duration};
+use std::{
+    cell::Cell,
+    cmp::min,
+    time::{Duration, Instant},
+};
 
 // NOTE: these work for Arctis Nova Pro but might not for different products!
 const SCREEN_REPORT_SPLIT_SZ: usize = 64;
 const SCREEN_REPORT_SIZE: usize = 1024;
-const BASE_STATION_VOLUME_MAX: u8 = 0x38;
+const SCREEN_REPORT_HEADER_SIZE: usize = 6;
+// Tallest column (in rows, including the offset of `dst_y` within its byte) that fits a report at the split width
+const SCREEN_REPORT_MAX_STRIDE_H: usize =
+    (SCREEN_REPORT_SIZE - SCREEN_REPORT_HEADER_SIZE) * 8 / SCREEN_REPORT_SPLIT_SZ / 8 * 8;
+/// Highest volume the base station accepts in `Device::set_volume`.
+pub const BASE_STATION_VOLUME_MAX: u8 = 0x38;
+const BRIGHTNESS_MIN: u8 = 0x01;
+const BRIGHTNESS_MAX: u8 = 0x0a;
 const DEVICE_WIDTH: usize = 128;
 const DEVICE_HEIGHT: usize = 64;
+const DRAW_REPORT_ID: u8 = 0x06;
+const TEST_PATTERN_SWEEP_STEP: Duration = Duration::from_millis(20);
 
 type DrawReport = [u8; SCREEN_REPORT_SIZE];
 
@@ -55,42 +68,74 @@ fn prepare_drawables_for_report(
     let src_x = (dst_x_start as isize - x) as usize;
     let src_y = (dst_y_start as isize - y) as usize;
 
-    let mut drawables = Vec::with_capacity(w.div_ceil(SCREEN_REPORT_SPLIT_SZ));
     let splits = w.div_ceil(SCREEN_REPORT_SPLIT_SZ);
-    for i in 0..splits {
-        let chunk_w = min(SCREEN_REPORT_SPLIT_SZ, w - i * SCREEN_REPORT_SPLIT_SZ);
-        drawables.push(ReportDrawableSpec {
-            w: chunk_w,
-            h,
-            dst_x: dst_x_start + (i * SCREEN_REPORT_SPLIT_SZ),
-            dst_y: dst_y_start,
-            src_x: src_x + i * SCREEN_REPORT_SPLIT_SZ,
-            src_y,
-        });
+    let mut drawables = Vec::with_capacity(splits);
+    // The area is tiled into rows of report columns, each split into chunks of at most the split width
+    let mut row = 0;
+    while row < h {
+        let dst_y = dst_y_start + row;
+        let chunk_h = min(h - row, SCREEN_REPORT_MAX_STRIDE_H - dst_y % 8);
+        for i in 0..splits {
+            let chunk_w = min(SCREEN_REPORT_SPLIT_SZ, w - i * SCREEN_REPORT_SPLIT_SZ);
+            drawables.push(ReportDrawableSpec {
+                w: chunk_w,
+                h: chunk_h,
+                dst_x: dst_x_start + (i * SCREEN_REPORT_SPLIT_SZ),
+                dst_y,
+                src_x: src_x + i * SCREEN_REPORT_SPLIT_SZ,
+                src_y: src_y + row,
+            });
+        }
+        row += chunk_h;
     }
     drawables
 }
 
-fn create_report_for_drawable(bitmap: &Bitmap, d: ReportDrawableSpec) -> DrawReport {
+// Rows per report column, padded to whole bytes with `dst_y` starting at its bit within the first byte
+fn report_stride_h(d: &ReportDrawableSpec) -> usize {
+    (d.dst_y % 8 + d.h).div_ceil(8) * 8
+}
+
+// Number of bytes of a report that a drawable uses, including the header
+fn report_used_len(d: &ReportDrawableSpec) -> usize {
+    SCREEN_REPORT_HEADER_SIZE + (d.w * report_stride_h(d)).div_ceil(8)
+}
+
+fn create_report_for_drawable(bitmap: &Bitmap, d: ReportDrawableSpec) -> anyhow::Result<DrawReport> {
+    let stride_h = report_stride_h(&d);
+    if report_used_len(&d) > SCREEN_REPORT_SIZE {
+        bail!("{}x{} drawable at y={} doesn't fit in a report", d.w, d.h, d.dst_y);
+    }
     let mut report: DrawReport = [0; SCREEN_REPORT_SIZE];
-    report[0] = 0x06; // hid report id
+    report[0] = DRAW_REPORT_ID; // hid report id
     report[1] = 0x93; // command id
-    report[2] = d.dst_x as u8;
-    report[3] = d.dst_y as u8;
-    report[4] = d.w as u8;
-    report[5] = d.h as u8;
-    let stride_h = (d.dst_y.wrapping_rem(8) + d.h).div_ceil(8) * 8;
+    report[2] = u8::try_from(d.dst_x)?;
+    report[3] = u8::try_from(d.dst_y)?;
+    report[4] = u8::try_from(d.w)?;
+    report[5] = u8::try_from(d.h)?;
     for y in 0..d.h {
         for x in 0..d.w {
             // NOTE: report has columns rather than rows
             let ri = x * stride_h + y;
             let pi = (d.src_y + y) * bitmap.w + (d.src_x + x);
-            let report_i = (ri / 8) + 6;
-            debug_assert!(report_i < SCREEN_REPORT_SIZE);
+            let report_i = (ri / 8) + SCREEN_REPORT_HEADER_SIZE;
             report[report_i] |= (bitmap.data[pi] as u8) << (ri % 8);
         }
     }
-    report
+    Ok(report)
+}
+
+// Full-screen patterns of `Device::draw_test_pattern`: all on, all off and both phases of a checkerboard.
+fn test_patterns(w: usize, h: usize) -> Vec<Bitmap> {
+    let mut checkerboard = Bitmap::new(w, h, false);
+    for y in 0..h {
+        for x in 0..w {
+            checkerboard.data.set(x + y * w, (x + y) % 2 == 0);
+        }
+    }
+    let mut inverse = checkerboard.clone();
+    inverse.invert();
+    vec![Bitmap::new(w, h, true), Bitmap::new(w, h, false), checkerboard, inverse]
 }
 
 #[derive(Debug)]
@@ -107,12 +152,157 @@ pub enum DeviceEvent {
         bluetooth: bool,
         bluetooth_on: bool,
     },
+    /// An event this library doesn't understand (yet), only reported after `Device::set_report_unknown_events`.
+    /// `data` is the rest of the report after the command byte.
+    Unknown {
+        command: u8,
+        data: Vec<u8>,
+    },
+}
+
+/// A `DeviceEvent` along with when it was read from the device.
+#[derive(Debug)]
+pub struct TimedEvent {
+    pub event: DeviceEvent,
+    pub time: Instant,
+}
+
+/// Identification of a connected device, as reported by USB.
+#[derive(Clone, Debug)]
+pub struct DeviceInfo {
+    pub product: String,
+    pub vendor_id: u16,
+    pub product_id: u16,
+    pub serial: Option<String>,
+    pub interface: i32,
+    /// Firmware version from the USB device release number, e.g. `1.05`.
+    pub firmware: Option<String>,
+}
+impl DeviceInfo {
+    fn from_hid(info: &hidapi::DeviceInfo) -> Self {
+        DeviceInfo {
+            product: info.product_string().unwrap_or("Unknown").to_string(),
+            vendor_id: info.vendor_id(),
+            product_id: info.product_id(),
+            serial: info.serial_number().map(str::to_string),
+            interface: info.interface_number(),
+            firmware: format_release_number(info.release_number()),
+        }
+    }
+}
+
+// Formats a BCD USB release number (bcdDevice) as `major.minor`. Zero means the device didn't report one.
+fn format_release_number(release: u16) -> Option<String> {
+    (release != 0).then(|| format!("{:x}.{:02x}", release >> 8, release & 0xff))
+}
+
+// Returns the size in bytes of feature report `report_id` in a HID report descriptor, excluding the report ID byte.
+fn feature_report_len(desc: &[u8], report_id: u8) -> Option<usize> {
+    let mut i = 0;
+    let mut report_size = 0;
+    let mut report_count = 0;
+    let mut current_id = 0;
+    let mut stack = vec![];
+    let mut bits = 0;
+    while i < desc.len() {
+        let prefix = desc[i];
+        if prefix == 0xfe {
+            // Long item, whose data size is in the next byte
+            i += 3 + *desc.get(i + 1)? as usize;
+            continue;
+        }
+        let size = [0, 1, 2, 4][(prefix & 0x03) as usize];
+        let data = desc.get(i + 1..i + 1 + size)?;
+        let value = data.iter().rev().fold(0usize, |acc, b| (acc << 8) | *b as usize);
+        match prefix & 0xfc {
+            0x74 => report_size = value,
+            0x84 => current_id = value,
+            0x94 => report_count = value,
+            0xa4 => stack.push((report_size, report_count, current_id)),
+            0xb4 => (report_size, report_count, current_id) = stack.pop()?,
+            0xb0 if current_id == report_id as usize => bits += report_size * report_count,
+            _ => {}
+        }
+        i += 1 + size;
+    }
+    (bits > 0).then(|| bits.div_ceil(8))
+}
+
+// The report descriptor doesn't describe the panel, so all supported models are assumed to have the same screen.
+// A draw report of another size means a model we don't know, which is worth a warning.
+fn check_draw_report_len(oled_dev: &HidDevice) {
+    let mut buf = [0u8; MAX_REPORT_DESCRIPTOR_SIZE];
+    let report_len = oled_dev
+        .get_report_descriptor(&mut buf)
+        .ok()
+        .and_then(|sz| feature_report_len(&buf[..sz], DRAW_REPORT_ID));
+    // Not every platform can read the descriptor, so only a size that was read and differs is worth a warning
+    if let Some(len) = report_len.filter(|len| len + 1 != SCREEN_REPORT_SIZE) {
+        eprintln!("warning: unknown draw report size {len}, assuming a {DEVICE_WIDTH}x{DEVICE_HEIGHT} screen");
+    }
 }
 
+const KNOWN_DEVICES: &[(u16, u16, &str)] = &[
+    (0x1038, 0x12cb, "SteelSeries Arctis Nova Pro Wired"),
+    (0x1038, 0x12cd, "SteelSeries Arctis Nova Pro Wired (Xbox)"),
+    (0x1038, 0x12e0, "SteelSeries Arctis Nova Pro Wireless"),
+    (0x1038, 0x12e5, "SteelSeries Arctis Nova Pro Wireless (Xbox)"),
+    (0x1038, 0x225d, "SteelSeries Arctis Nova Pro Wireless (Xbox White)"),
+];
+
+/// Base station volume for a percentage from 0 to 100, rounded to the nearest level.
+pub fn volume_from_percent(percent: u8) -> u8 {
+    (((percent.min(100) as u16) * (BASE_STATION_VOLUME_MAX as u16) + 50) / 100) as u8
+}
+
+/// Percentage for a base station volume, rounded to the nearest whole percent.
+pub fn volume_to_percent(volume: u8) -> u8 {
+    (((volume.min(BASE_STATION_VOLUME_MAX) as u16) * 100 + (BASE_STATION_VOLUME_MAX as u16 / 2))
+        / BASE_STATION_VOLUME_MAX as u16) as u8
+}
+
+/// The `(vendor id, product id, name)` of every device `Device::connect` looks for.
+pub fn known_devices() -> &'static [(u16, u16, &'static str)] {
+    KNOWN_DEVICES
+}
+
+fn is_supported_device(d: &hidapi::DeviceInfo) -> bool {
+    KNOWN_DEVICES
+        .iter()
+        .any(|(vendor_id, product_id, _)| d.vendor_id() == *vendor_id && d.product_id() == *product_id)
+        && d.interface_number() == 4
+}
+
+/// Checks whether a supported device is plugged in, without connecting to it.
+pub struct DeviceWatcher {
+    api: HidApi,
+}
+impl DeviceWatcher {
+    pub fn new() -> anyhow::Result<DeviceWatcher> {
+        Ok(DeviceWatcher { api: HidApi::new()? })
+    }
+
+    /// Refresh the USB device list and return whether any supported device is in it.
+    pub fn is_present(&mut self) -> anyhow::Result<bool> {
+        self.api.refresh_devices()?;
+        Ok(self.api.device_list().any(is_supported_device))
+    }
+}
+
+// Most input reports read per `get_events` call when events come from the OLED handle
+const MAX_SHARED_HANDLE_READS: usize = 8;
+
 pub struct Device {
     oled_dev: HidDevice,
     info_dev: Option<HidDevice>,
     info_blocking_mode: Option<bool>,
+    // Whether `oled_dev` was switched to non-blocking reads, for reading events without a separate info handle
+    oled_nonblocking: bool,
+    info: DeviceInfo,
+    // Last requested settings, re-applied by `reconnect`
+    brightness: Cell<Option<u8>>,
+    volume: Cell<Option<u8>>,
+    report_unknown_events: bool,
     pub width: usize,
     pub height: usize,
 }
@@ -122,19 +312,7 @@ impl Device {
         let api = HidApi::new().unwrap();
 
         // Find all connected devices matching given Vendor/Product IDs and interface
-        let device_infos: Vec<_> = api
-            .device_list()
-            .filter(|d| {
-                d.vendor_id() == 0x1038 // SteelSeries
-        && [
-            0x12cb, // Arctis Nova Pro Wired
-            0x12cd, // Arctis Nova Pro Wired (Xbox)
-            0x12e0, // Arctis Nova Pro Wireless
-            0x12e5, // Arctis Nova Pro Wireless (Xbox)
-            0x225d, // Arctis Nova Pro Wireless (Xbox White)
-        ].contains(&d.product_id()) && d.interface_number() == 4
-            })
-            .collect();
+        let device_infos: Vec<_> = api.device_list().filter(|d| is_supported_device(d)).collect();
 
         // On some platforms this can be duplicated or collapsed, so we only require at least one candidate.
         if device_infos.is_empty() {
@@ -144,7 +322,7 @@ impl Device {
         // If all entries point to the same path, use one handle for drawing and best-effort second handle for events.
         let all_same_path = device_infos.iter().all(|d| d.path() == device_infos[0].path());
 
-        let (oled_dev, info_dev) = if all_same_path {
+        let (oled_dev, info_dev, info) = if all_same_path {
             let oled_dev = device_infos[0]
                 .open_device(&api)
                 .map_err(|err| anyhow::anyhow!("Failed to connect to USB device: {err}"))?;
@@ -157,7 +335,7 @@ impl Device {
                     None
                 }
             };
-            (oled_dev, info_dev)
+            (oled_dev, info_dev, DeviceInfo::from_hid(device_infos[0]))
 
         // On platforms exposing separate interfaces, pick OLED by descriptor and best-effort select an info interface.
         } else {
@@ -187,6 +365,7 @@ impl Device {
             };
             _ = device_reports.swap_remove(oled_dev_idx);
             let oled_dev = devices.swap_remove(oled_dev_idx);
+            let info = DeviceInfo::from_hid(device_infos[oled_dev_idx]);
 
             // Prefer known info descriptor (0x00), otherwise fallback to any non-OLED descriptor.
             let info_dev = if let Some(info_dev_idx) = device_reports.iter().position(|desc| desc.get(1) == Some(&0x00))
@@ -200,18 +379,29 @@ impl Device {
                 None
             };
 
-            (oled_dev, info_dev)
+            (oled_dev, info_dev, info)
         };
 
+        check_draw_report_len(&oled_dev);
         Ok(Device {
             oled_dev,
             info_dev,
             info_blocking_mode: None,
+            oled_nonblocking: false,
+            info,
+            brightness: Cell::new(None),
+            volume: Cell::new(None),
+            report_unknown_events: false,
             width: DEVICE_WIDTH,
             height: DEVICE_HEIGHT,
         })
     }
 
+    /// Product and firmware info of the connected device.
+    pub fn info(&self) -> &DeviceInfo {
+        &self.info
+    }
+
     /// Dump the full device tree info for all SteelSeries devices to stdout for debug purposes
     pub fn dump_devices() {
         let api = HidApi::new().unwrap();
@@ -229,6 +419,7 @@ impl Device {
         for info in device_infos {
             println!("product={}", info.product_string().unwrap_or("?"));
             println!("pid={:#04x}", info.product_id());
+            println!("release={:#06x}", info.release_number());
             println!("interface={}", info.interface_number());
             println!("path={}", info.path().to_string_lossy());
             println!("usage={}", info.usage());
@@ -246,15 +437,24 @@ impl Device {
         }
     }
 
-    /// Reconnect to a device.
+    /// Reconnect to a device. Brightness, volume and `set_report_unknown_events` are carried over from this `Device`.
     pub fn reconnect(&mut self) -> anyhow::Result<()> {
+        let (brightness, volume) = (self.brightness.get(), self.volume.get());
+        let report_unknown_events = self.report_unknown_events;
         *self = Self::connect()?;
+        self.report_unknown_events = report_unknown_events;
+        if let Some(value) = brightness {
+            self.set_brightness(value)?;
+        }
+        if let Some(value) = volume {
+            self.set_volume(value)?;
+        }
         Ok(())
     }
 
     // Creates a HID report for a `ReportDrawable`
     // The Bitmap must already be within the report limits (from `split_for_report`)
-    fn create_report(&self, d: &ReportDrawable) -> DrawReport {
+    fn create_report(&self, d: &ReportDrawable) -> anyhow::Result<DrawReport> {
         create_report_for_drawable(
             d.bitmap,
             ReportDrawableSpec {
@@ -288,7 +488,7 @@ impl Device {
     pub fn draw(&self, bitmap: &Bitmap, x: isize, y: isize) -> anyhow::Result<()> {
         let drawables = self.prepare_for_report(bitmap, x, y);
         for drawable in drawables {
-            let report = self.create_report(&drawable);
+            let report = self.create_report(&drawable)?;
             self.retry_report(&report)?;
         }
         Ok(())
@@ -310,13 +510,14 @@ impl Device {
         }
     }
 
-    /// Set screen brightness.
+    /// Set screen brightness, from 1 to 10. Values outside of that are an error.
     pub fn set_brightness(&self, value: u8) -> anyhow::Result<()> {
-        if value < 0x01 {
-            bail!("brightness too low");
-        } else if value > 0x0a {
-            bail!("brightness too high");
+        if value < BRIGHTNESS_MIN {
+            bail!("brightness {value} too low, must be {BRIGHTNESS_MIN}-{BRIGHTNESS_MAX}");
+        } else if value > BRIGHTNESS_MAX {
+            bail!("brightness {value} too high, must be {BRIGHTNESS_MIN}-{BRIGHTNESS_MAX}");
         }
+        self.brightness.set(Some(value));
         let mut report = [0; 64];
         report[0] = 0x06; // hid report id
         report[1] = 0x85; // command id
@@ -325,11 +526,17 @@ impl Device {
         Ok(())
     }
 
+    /// Set screen brightness, saturating values outside of 1 to 10 into that range.
+    pub fn set_brightness_clamped(&self, value: u8) -> anyhow::Result<()> {
+        self.set_brightness(value.clamp(BRIGHTNESS_MIN, BRIGHTNESS_MAX))
+    }
+
     /// Set base station volume where `0` is mute and `56` is max volume.
     pub fn set_volume(&self, value: u8) -> anyhow::Result<()> {
         if value > BASE_STATION_VOLUME_MAX {
             bail!("volume too high");
         }
+        self.volume.set(Some(value));
         let mut report = [0; 64];
         report[0] = 0x06; // hid report id
         report[1] = 0x25; // command id
@@ -338,6 +545,24 @@ impl Device {
         Ok(())
     }
 
+    /// Show a sequence of test patterns for diagnosing dead pixels or a misunderstood report format:
+    /// all pixels on, all off and a checkerboard in both phases, each for `delay`,
+    /// followed by a single lit column sweeping from left to right.
+    pub fn draw_test_pattern(&self, delay: Duration) -> anyhow::Result<()> {
+        for pattern in test_patterns(self.width, self.height) {
+            self.draw(&pattern, 0, 0)?;
+            std::thread::sleep(delay);
+        }
+        let column = Bitmap::new(1, self.height, true);
+        for x in 0..self.width {
+            let mut screen = Bitmap::new(self.width, self.height, false);
+            screen.blit(&column, x as isize, 0, false);
+            self.draw(&screen, 0, 0)?;
+            std::thread::sleep(TEST_PATTERN_SWEEP_STEP);
+        }
+        Ok(())
+    }
+
     /// Return to SteelSeries UI.
     pub fn return_to_ui(&self) -> anyhow::Result<()> {
         let mut report = [0; 64];
@@ -347,22 +572,21 @@ impl Device {
         Ok(())
     }
 
-    fn parse_event(buf: &[u8; 64]) -> Option<DeviceEvent> {
-        #[cfg(debug_assertions)]
-        println!("parse_event: {:x?}", buf);
-        if buf[0] != 7 {
+    // Parses an input report, which may be shorter than a full 64 bytes. Reports too short for their type are ignored.
+    fn parse_event(buf: &[u8]) -> Option<DeviceEvent> {
+        if buf.len() < 2 || buf[0] != 7 {
             return None;
         }
         Some(match buf[1] {
-            0x25 => DeviceEvent::Volume {
+            0x25 if buf.len() >= 3 => DeviceEvent::Volume {
                 volume: BASE_STATION_VOLUME_MAX.saturating_sub(buf[2]),
             },
-            0xb5 => DeviceEvent::HeadsetConnection {
+            0xb5 if buf.len() >= 5 => DeviceEvent::HeadsetConnection {
                 wireless: buf[4] == 8,
                 bluetooth: buf[3] == 1,
                 bluetooth_on: buf[2] == 4,
             },
-            0xb7 => DeviceEvent::Battery {
+            0xb7 if buf.len() >= 4 => DeviceEvent::Battery {
                 headset: buf[2],
                 charging: buf[3],
                 // NOTE: there's a chance `buf[4]` represents either the max value or simply just `8` for connected
@@ -371,6 +595,28 @@ impl Device {
         })
     }
 
+    fn parse_report(&self, buf: &[u8]) -> Option<DeviceEvent> {
+        Self::parse_event(buf).or_else(|| Self::parse_unknown_event(buf).filter(|_| self.report_unknown_events))
+    }
+
+    fn parse_unknown_event(buf: &[u8]) -> Option<DeviceEvent> {
+        (buf.len() >= 2 && buf[0] == 7).then(|| DeviceEvent::Unknown {
+            command: buf[1],
+            data: buf[2..].to_vec(),
+        })
+    }
+
+    /// Also return events that aren't recognized as `DeviceEvent::Unknown`, e.g. to log them for adding support.
+    /// Off by default.
+    pub fn set_report_unknown_events(&mut self, enabled: bool) {
+        self.report_unknown_events = enabled;
+    }
+
+    /// Whether events come from a separate info handle, so `poll_event` can block until one arrives.
+    pub fn has_info_handle(&self) -> bool {
+        self.info_dev.is_some()
+    }
+
     fn set_info_blocking_mode(&mut self, blocking: bool) -> anyhow::Result<()> {
         let Some(info_dev) = self.info_dev.as_ref() else {
             return Ok(());
@@ -383,7 +629,13 @@ impl Device {
     }
 
     /// Poll events from the device. This blocks until an event is returned.
+    /// Without a separate info handle this returns `None` right away, as a blocking read would hold up drawing.
     pub fn poll_event(&mut self) -> anyhow::Result<Option<DeviceEvent>> {
+        Ok(self.poll_timed_event()?.map(|timed| timed.event))
+    }
+
+    /// Like `poll_event`, but with the time the event was read.
+    pub fn poll_timed_event(&mut self) -> anyhow::Result<Option<TimedEvent>> {
         if self.info_dev.is_none() {
             return Ok(None);
         }
@@ -392,27 +644,42 @@ impl Device {
             return Ok(None);
         };
         let mut buf = [0u8; 64];
-        _ = info_dev.read(&mut buf)?;
-        Ok(Self::parse_event(&buf))
+        let len = info_dev.read(&mut buf)?;
+        let time = Instant::now();
+        Ok(self.parse_report(&buf[..len]).map(|event| TimedEvent { event, time }))
     }
 
     /// Return any pending events from the device. Non-blocking.
+    /// Without a separate info handle, events are read from the OLED handle instead, a few reports at a time.
     pub fn get_events(&mut self) -> anyhow::Result<Vec<DeviceEvent>> {
-        if self.info_dev.is_none() {
-            return Ok(vec![]);
-        }
-        self.set_info_blocking_mode(false)?;
-        let Some(info_dev) = self.info_dev.as_ref() else {
-            return Ok(vec![]);
+        Ok(self.get_timed_events()?.into_iter().map(|timed| timed.event).collect())
+    }
+
+    /// Like `get_events`, but with the time each event was read.
+    pub fn get_timed_events(&mut self) -> anyhow::Result<Vec<TimedEvent>> {
+        let max_reads = if self.info_dev.is_some() {
+            self.set_info_blocking_mode(false)?;
+            usize::MAX
+        } else {
+            // The OLED handle is shared with drawing, so never block on it and don't drain it indefinitely
+            if !self.oled_nonblocking {
+                self.oled_dev.set_blocking_mode(false)?;
+                self.oled_nonblocking = true;
+            }
+            MAX_SHARED_HANDLE_READS
         };
+        let dev = self.info_dev.as_ref().unwrap_or(&self.oled_dev);
         let mut events = Vec::with_capacity(4);
-        loop {
+        for _ in 0..max_reads {
             let mut buf = [0u8; 64];
-            let len = info_dev.read(&mut buf)?;
+            let len = dev.read(&mut buf)?;
             if len == 0 {
                 break;
-            } else if let Some(event) = Self::parse_event(&buf) {
-                events.push(event);
+            } else if let Some(event) = self.parse_report(&buf[..len]) {
+                events.push(TimedEvent {
+                    event,
+                    time: Instant::now(),
+                });
             }
         }
         Ok(events)
@@ -424,6 +691,95 @@ mod tests {
     use super::*;
     use proptest::prelude::*;
 
+    #[test]
+    fn volume_percentages_round_trip() {
+        assert_eq!(volume_from_percent(0), 0);
+        assert_eq!(volume_from_percent(100), BASE_STATION_VOLUME_MAX);
+        assert_eq!(volume_from_percent(255), BASE_STATION_VOLUME_MAX);
+        for volume in 0..=BASE_STATION_VOLUME_MAX {
+            assert_eq!(volume_from_percent(volume_to_percent(volume)), volume);
+        }
+    }
+
+    #[test]
+    fn feature_report_len_sums_items_of_report() {
+        let desc = [
+            0x06, 0xc0, 0xff, // Usage Page (vendor)
+            0x85, 0x06, // Report ID (6)
+            0x75, 0x08, // Report Size (8)
+            0x96, 0xff, 0x03, // Report Count (1023)
+            0xb1, 0x02, // Feature
+            0xa4, // Push
+            0x85, 0x07, // Report ID (7)
+            0x95, 0x3f, // Report Count (63)
+            0xb1, 0x02, // Feature
+            0xb4, // Pop
+            0x95, 0x01, // Report Count (1)
+            0x81, 0x02, // Input
+        ];
+        assert_eq!(feature_report_len(&desc, 0x06), Some(SCREEN_REPORT_SIZE - 1));
+        assert_eq!(feature_report_len(&desc, 0x07), Some(63));
+        assert_eq!(feature_report_len(&desc, 0x08), None);
+        assert_eq!(feature_report_len(&desc[..8], 0x06), None);
+    }
+
+    #[test]
+    fn test_patterns_cover_both_pixel_states() {
+        let patterns = test_patterns(4, 2);
+        assert_eq!(patterns.len(), 4);
+        assert!(patterns[0].data.all());
+        assert!(patterns[1].data.none());
+        assert_eq!(patterns[2], Bitmap::from_ascii(&["#.#.", ".#.#"], '#'));
+        assert_eq!(patterns[3], Bitmap::from_ascii(&[".#.#", "#.#."], '#'));
+    }
+
+    #[test]
+    fn parse_event_ignores_short_reports() {
+        let connection = [0x07, 0xb5, 0x04, 0x01, 0x08];
+        assert!(matches!(
+            Device::parse_event(&connection),
+            Some(DeviceEvent::HeadsetConnection {
+                wireless: true,
+                bluetooth: true,
+                bluetooth_on: true
+            })
+        ));
+        for len in 0..connection.len() {
+            assert!(Device::parse_event(&connection[..len]).is_none());
+        }
+        assert!(matches!(
+            Device::parse_event(&[0x07, 0x25, 0x08]),
+            Some(DeviceEvent::Volume { volume: 0x30 })
+        ));
+        assert!(Device::parse_event(&[0x07, 0xb7, 0x05]).is_none());
+        assert!(Device::parse_event(&[0x07, 0x42, 0x00, 0x00, 0x00]).is_none());
+    }
+
+    #[test]
+    fn unknown_events_keep_command_and_data() {
+        assert!(matches!(
+            Device::parse_unknown_event(&[0x07, 0x42, 0x01, 0x02]),
+            Some(DeviceEvent::Unknown { command: 0x42, data }) if data == [0x01, 0x02]
+        ));
+        assert!(Device::parse_unknown_event(&[0x06, 0x42]).is_none());
+        assert!(Device::parse_unknown_event(&[0x07]).is_none());
+    }
+
+    #[test]
+    fn known_devices_are_listed_once() {
+        let devices = known_devices();
+        for (i, (vendor_id, product_id, _)) in devices.iter().enumerate() {
+            assert!(devices[i + 1..].iter().all(|d| (d.0, d.1) != (*vendor_id, *product_id)));
+        }
+    }
+
+    #[test]
+    fn release_number_is_formatted_as_bcd() {
+        assert_eq!(format_release_number(0x0105).as_deref(), Some("1.05"));
+        assert_eq!(format_release_number(0x1234).as_deref(), Some("12.34"));
+        assert_eq!(format_release_number(0), None);
+    }
+
     proptest! {
         #[test]
         fn prepare_drawables_and_reports_are_bounds_safe(
@@ -444,7 +800,48 @@ mod tests {
                 prop_assert!(d.src_y + d.h <= bitmap_h);
                 prop_assert!(d.dst_x + d.w <= DEVICE_WIDTH);
                 prop_assert!(d.dst_y + d.h <= DEVICE_HEIGHT);
-                let _ = create_report_for_drawable(&bitmap, d);
+                prop_assert!(create_report_for_drawable(&bitmap, d).is_ok());
+            }
+        }
+
+        #[test]
+        fn drawables_fit_reports_for_any_screen_size(
+            screen_w in 1usize..=256,
+            screen_h in 1usize..=256,
+            bitmap_w in 0usize..512,
+            bitmap_h in 0usize..512,
+            x in -256isize..256,
+            y in -256isize..256
+        ) {
+            let bitmap = Bitmap::new(bitmap_w, bitmap_h, true);
+            let drawables = prepare_drawables_for_report(&bitmap, x, y, screen_w, screen_h);
+            for d in drawables {
+                prop_assert!(d.w <= SCREEN_REPORT_SPLIT_SZ);
+                prop_assert!(d.h <= SCREEN_REPORT_MAX_STRIDE_H);
+                prop_assert!(report_used_len(&d) <= SCREEN_REPORT_SIZE);
+                prop_assert!(d.dst_x + d.w <= screen_w);
+                prop_assert!(d.dst_y + d.h <= screen_h);
+                prop_assert!(d.src_x + d.w <= bitmap_w);
+                prop_assert!(d.src_y + d.h <= bitmap_h);
+                prop_assert!(create_report_for_drawable(&bitmap, d).is_ok());
+            }
+        }
+
+        #[test]
+        fn tall_drawables_are_split_into_reports_that_fit(
+            screen_h in 1usize..=256,
+            bitmap_h in 0usize..512,
+            y in -256isize..256
+        ) {
+            let bitmap = Bitmap::new(DEVICE_WIDTH, bitmap_h, true);
+            let drawables = prepare_drawables_for_report(&bitmap, 0, y, DEVICE_WIDTH, screen_h);
+            let visible_h = (y + bitmap_h as isize).clamp(0, screen_h as isize) - y.clamp(0, screen_h as isize);
+            let covered: usize = drawables.iter().map(|d| d.w * d.h).sum();
+            prop_assert_eq!(covered, DEVICE_WIDTH * visible_h.max(0) as usize);
+            for d in drawables {
+                prop_assert!(d.dst_y + d.h <= screen_h);
+                prop_assert!(d.src_y + d.h <= bitmap_h);
+                prop_assert!(create_report_for_drawable(&bitmap, d).is_ok());
             }
         }
     }